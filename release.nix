@@ -12,12 +12,17 @@
     optLevel = 0;
     lto = false;
   } ];
-  tests = [ "opt1" "opt2" "lto" "fail" "fail-black-box" ];
-  testsFor = { debugAssertions, optLevel, lto }: with pkgs.lib;
-    optional (debugAssertions || isString optLevel || optLevel > 0) "opt1"
-    ++ optional (debugAssertions || isString optLevel || optLevel > 1) "opt2"
-    ++ optional (debugAssertions || ((isString optLevel || optLevel > (if lto == "fat" then 1 else 0)) && lto != false)) "lto"
-    ++ optionals debugAssertions [ "fail" "fail-black-box" ];
+  tests = [ "opt1" "opt2" "lto" "assert" "ops" "unwrap" "fail" "fail-black-box" ];
+  # Which tests link in which configurations, shared with `cargo reachability matrix`
+  expectations = (builtins.fromTOML (builtins.readFile ./reachability.toml)).matrix;
+  ltoMode = lto: if lto == false then "off" else lto;
+  linksIn = { optLevel, lto, ... }: entry:
+    (!(entry ? "opt-level") || builtins.elem optLevel entry."opt-level")
+    && (!(entry ? lto) || builtins.elem (ltoMode lto) entry.lto);
+  testsFor = { debugAssertions, optLevel, lto }@opt: with pkgs.lib; filter (test:
+    let entries = filter (entry: entry.target == test) expectations."expect-link";
+    in debugAssertions || (!(elem test expectations."expect-fail") && (entries == [ ] || any (linksIn opt) entries))
+  ) tests;
   failingTests = { debugAssertions, optLevel, lto }@opt: with pkgs.lib;
     subtractLists (testsFor opt) tests;
 
@@ -62,6 +67,13 @@
       echo "[cargo] test $ctests"
       cargo test --features unstable-internal-test --doc
       cargo test --features unstable-internal-test --lib $ctestArgs
+      cargo test --features warn --test warn --test fail --test fail-black-box
+      RUSTFLAGS='--cfg reachability="panic"' cargo test --features unstable-internal-test --test fail --test fail-black-box
+      RUSTFLAGS='--cfg reachability_unchecked="checked"' cargo test --lib
+      if cargo build --features unstable-internal-test,always-static --test fail; then
+        echo "expected fail failure with always-static" >&2
+        exit 1
+      fi
 
       for ctest in $ctestFailures; do
         echo "[cargo] test fail $ctest"
@@ -0,0 +1,224 @@
+//! Baselines of unproven sites for `cargo reachability baseline`.
+//!
+//! A baseline records, for each matrix configuration, the sites that were not
+//! eliminated when it was written, so every other site is known to be proven.
+//! Checking a build against it only fails on sites missing from it, whether a
+//! previously proven site regressed or a new site isn't proven, and lists the
+//! sites that became proven so the baseline can be tightened. The file is
+//! plain text meant to be checked in:
+//!
+//! ```text
+//! rustc 1.80.1 (3f5fd8dd4 2024-08-06)
+//!
+//! [opt-level=3 lto=fat]
+//! app src/net.rs: bad header
+//! app src/main.rs:12:5
+//! ```
+//!
+//! Sites are recorded by crate, file and message, so edits elsewhere in the
+//! file don't churn the baseline. Sites without a message fall back to their
+//! line and column.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::matrix::{Outcome, Profile, Results};
+use crate::symbol::{self, Site};
+
+/// The default baseline file name.
+pub const BASELINE_FILE: &str = "reachability-baseline.txt";
+
+/// The unproven sites of each configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Baseline {
+    /// `rustc --version` of the builds, without the leading `rustc`.
+    pub rustc: Option<String>,
+    /// Site keys and how many sites share each, by configuration.
+    pub profiles: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+/// The key a site is recorded under.
+pub fn key(site: &Site) -> String {
+    match site.message {
+        Some(message) => format!("{} {}: {}", site.crate_name(), site.file, message),
+        None => format!("{} {}:{}:{}", site.crate_name(), site.file, site.line, site.column),
+    }
+}
+
+/// The file, and line and column if recorded, of the site behind `key`.
+pub fn key_file(key: &str) -> &str {
+    let path = key.split_once(' ').map_or(key, |(_, path)| path);
+    path.split_once(": ").map_or(path, |(file, _)| file)
+}
+
+/// A difference between a baseline and a build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A site that isn't proven but was either proven or absent.
+    Regressed { profile: String, key: String },
+    /// A site recorded as unproven that is now proven.
+    Proven { profile: String, key: String },
+    /// A configuration the baseline has no record of.
+    Missing { profile: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Regressed { profile, key } => write!(f, "{}: + {}", profile, key),
+            Change::Proven { profile, key } => write!(f, "{}: - {}", profile, key),
+            Change::Missing { profile } => write!(f, "{}: not in the baseline", profile),
+        }
+    }
+}
+
+impl Baseline {
+    /// The baseline of matrix `results`. Fails with the first target that
+    /// failed to build for another reason than a link failure.
+    pub fn from_results(rustc: Option<String>, results: &[Results]) -> Result<Self, String> {
+        let mut baseline = Baseline { rustc, profiles: BTreeMap::new() };
+        for result in results {
+            let mut symbols = Vec::new();
+            for (target, outcome) in &result.targets {
+                match outcome {
+                    Outcome::Linked => (),
+                    Outcome::LinkFailed(diagnostics) => symbols.extend(diagnostics.iter().map(|d| &d.symbol[..])),
+                    Outcome::Failed(error) => return Err(format!("{}: {}: {}", result.profile, target, error)),
+                }
+            }
+            symbols.sort_unstable();
+            symbols.dedup();
+
+            let sites = baseline.profiles.entry(result.profile.to_string()).or_default();
+            for site in symbols.into_iter().filter_map(symbol::decode) {
+                *sites.entry(key(&site)).or_default() += 1;
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// Parse a baseline file.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut baseline = Baseline::default();
+        let mut profile = None;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if !Profile::matrix().iter().any(|p| p.to_string() == name) {
+                    return Err(format!("line {}: unknown configuration `{}`", i + 1, name));
+                }
+                baseline.profiles.entry(name.to_owned()).or_default();
+                profile = Some(name.to_owned());
+            } else if let Some(sites) = profile.as_ref().and_then(|p| baseline.profiles.get_mut(p)) {
+                *sites.entry(line.to_owned()).or_default() += 1;
+            } else if let (Some(rustc), None) = (line.strip_prefix("rustc "), &baseline.rustc) {
+                baseline.rustc = Some(rustc.to_owned());
+            } else {
+                return Err(format!("line {}: expected a `[configuration]`", i + 1));
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// The differences of `current` from this baseline, for the
+    /// configurations in `current`.
+    pub fn diff(&self, current: &Baseline) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (profile, sites) in &current.profiles {
+            let baseline = match self.profiles.get(profile) {
+                Some(baseline) => baseline,
+                None => {
+                    changes.push(Change::Missing { profile: profile.clone() });
+                    continue;
+                },
+            };
+            for (key, &count) in sites {
+                for _ in baseline.get(key).copied().unwrap_or(0)..count {
+                    changes.push(Change::Regressed { profile: profile.clone(), key: key.clone() });
+                }
+            }
+            for (key, &count) in baseline {
+                for _ in sites.get(key).copied().unwrap_or(0)..count {
+                    changes.push(Change::Proven { profile: profile.clone(), key: key.clone() });
+                }
+            }
+        }
+        changes
+    }
+
+    /// Record `current`, keeping the configurations it didn't build.
+    pub fn update(&mut self, current: Baseline) {
+        self.rustc = current.rustc;
+        self.profiles.extend(current.profiles);
+    }
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# `unreachable_static!()` sites that were not eliminated, by configuration.")?;
+        writeln!(f, "# Written by `cargo reachability baseline --update`.")?;
+        if let Some(rustc) = &self.rustc {
+            writeln!(f, "rustc {}", rustc)?;
+        }
+        // In matrix order rather than by name
+        for profile in Profile::matrix().iter().map(Profile::to_string) {
+            if let Some(sites) = self.profiles.get(&profile) {
+                write!(f, "\n[{}]\n", profile)?;
+                for (key, &count) in sites {
+                    for _ in 0..count {
+                        writeln!(f, "{}", key)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ld::Diagnostic;
+
+    fn results(profile: Profile, symbols: &[&str]) -> Results {
+        let diagnostics = symbols.iter().map(|&s| Diagnostic { symbol: s.into(), functions: Vec::new() }).collect();
+        Results { profile, targets: vec![("app".into(), Outcome::LinkFailed(diagnostics)), ("opt1".into(), Outcome::Linked)] }
+    }
+
+    #[test]
+    fn ratchet() {
+        let profiles = Profile::matrix();
+        let recorded = Baseline::from_results(Some("1.80.1".into()), &[
+            results(profiles[11], &[
+                "___unreachable_static___@app::net@src/net.rs:88:13: bad header",
+                "___unreachable_static___@app@src/main.rs:12:5",
+            ]),
+            results(profiles[0], &[]),
+        ]).unwrap();
+        let text = recorded.to_string();
+        assert!(text.ends_with("rustc 1.80.1\n\n[opt-level=0 lto=off]\n\n[opt-level=3 lto=fat]\napp src/main.rs:12:5\napp src/net.rs: bad header\n"));
+        assert_eq!(Baseline::parse(&text).unwrap(), recorded);
+
+        // The message site moved and the other one is now proven
+        let current = Baseline::from_results(Some("1.81.0".into()), &[
+            results(profiles[11], &[
+                "___unreachable_static___@app::net@src/net.rs:90:13: bad header",
+                "___unreachable_static___@app@src/main.rs:20:5",
+            ]),
+            results(profiles[14], &[]),
+        ]).unwrap();
+        assert_eq!(recorded.diff(&current), [
+            Change::Regressed { profile: "opt-level=3 lto=fat".into(), key: "app src/main.rs:20:5".into() },
+            Change::Proven { profile: "opt-level=3 lto=fat".into(), key: "app src/main.rs:12:5".into() },
+            Change::Missing { profile: "opt-level=s lto=fat".into() },
+        ]);
+
+        assert_eq!(key_file("app src/main.rs:20:5"), "src/main.rs:20:5");
+        assert_eq!(key_file("app src/net.rs: bad header: eof"), "src/net.rs");
+
+        assert_eq!(Baseline::parse("[opt-level=9 lto=off]"), Err("line 1: unknown configuration `opt-level=9 lto=off`".into()));
+        assert_eq!(Baseline::parse("app src/main.rs:1:1"), Err("line 1: expected a `[configuration]`".into()));
+    }
+}
@@ -0,0 +1,747 @@
+//! `cargo reachability`: workspace tooling for `unreachable_static!()` sites.
+
+use std::env;
+use std::ffi::OsString;
+use std::process;
+use reachability::matrix::{self, ExpectLink, Lto, OptLevel, Outcome, Profile, Table};
+use reachability::{bisect, dwarf, matrix::Results, object, symbol};
+use reachability::baseline::{self, Baseline, Change, BASELINE_FILE};
+use reachability::build::Config;
+use reachability::inventory::{self, Kind, Package, Use};
+use reachability::junit::JUnit;
+use reachability::ld::Diagnostic;
+use reachability::remarks::{self, Remark};
+use reachability::report::{Finding, Format, Location, Report, Rule};
+use reachability::scope::Scope;
+
+const USAGE: &str = "\
+usage: cargo reachability <command> [options]
+
+commands:
+    matrix    build every binary and test target across opt-levels and LTO modes
+    baseline  check the matrix against a baseline of unproven sites, or update it
+    bisect    find the sites failing a link by rebuilding with subsets enforced
+    explain   show the optimizations LLVM missed around the sites failing a link
+    list      list the uses of this crate's macros and methods in the sources
+    scan      list the sites referenced by object files, rlibs or executables
+    verify    list the sites registered by `warn` builds that survived linking";
+
+const MATRIX_USAGE: &str = "\
+usage: cargo reachability matrix [options] [-- <cargo build args>...]
+
+options:
+    --opt-level <levels>    comma separated opt-levels to build (default: 0,1,2,3,s,z)
+    --lto <modes>           comma separated LTO modes to build (default: off,thin,fat)
+    --expect-fail <target>  target expected to fail to link when sites are enforced
+    --expect-link <target>:<levels>[:<modes>]
+                            target expected to link only at these comma separated
+                            opt-levels and LTO modes, empty for all; repeat for
+                            more configurations
+    --junit <path>          also write the results as JUnit XML to <path>
+    --format <format>       `text`, `json` or `sarif` (default: text)
+
+Expectations are also read from `[matrix]` in `reachability.toml`, see
+`reachability::build`.";
+
+const BASELINE_USAGE: &str = "\
+usage: cargo reachability baseline [options] [-- <cargo build args>...]
+
+Builds the matrix like `matrix` and compares the sites that were not
+eliminated in each configuration against a baseline file. Fails on sites that
+aren't in the baseline, which either regressed or are new, and lists the sites
+that are now proven. With `--update`, writes the baseline instead.
+
+options:
+    --file <path>           the baseline file (default: reachability-baseline.txt)
+    --update                write the baseline rather than check it
+    --opt-level <levels>    comma separated opt-levels to build (default: 0,1,2,3,s,z)
+    --lto <modes>           comma separated LTO modes to build (default: off,thin,fat)
+    --format <format>       `text`, `json` or `sarif` (default: text)";
+
+const BISECT_USAGE: &str = "\
+usage: cargo reachability bisect [options] [-- <cargo build args>...]
+
+Finds the `unreachable_static!()` sites that make a link fail, for when the
+linker doesn't name them. Lists the sites that survive a build with every site
+in the `warn` mode, then rebuilds with subsets of them enforced and the rest
+falling back to panics, narrowing down to the smallest sets that fail.
+
+options:
+    --opt-level <level>     the opt-level to build (default: 3)
+    --lto <mode>            the LTO mode to build (default: off)
+    --format <format>       `text`, `json` or `sarif` (default: text)";
+
+const EXPLAIN_USAGE: &str = "\
+usage: cargo reachability explain [options] [-- <cargo build args>...]
+
+Rebuilds with LLVM optimization remarks (`-C remark`) and prints the missed
+optimizations in the functions holding each site that failed to link: calls
+that weren't inlined into them or that they weren't inlined into, and other
+passes' misses in the site's file.
+
+options:
+    --opt-level <level>     the opt-level to build (default: 3)
+    --lto <mode>            the LTO mode to build (default: off)
+    --passes <passes>       comma separated LLVM passes to report (default: all)
+    --format <format>       `text`, `json` or `sarif` (default: text)";
+
+const LIST_USAGE: &str = "\
+usage: cargo reachability list [options] [-- <cargo metadata args>...]
+
+Lists every use of `unreachable_static!`, `unreachable_unchecked!` and the
+other macros and `unwrap_*` methods in the workspace sources, grouped by crate
+and by whether they are statically proven or unchecked.
+
+With `--audit`, lists the unchecked uses instead that have no comment holding
+the marker next to them, on their line, in their `unsafe` block, or directly
+above either, and fails if there are any.
+
+options:
+    --deps               include the sources of every dependency
+    --summary            only print the number of uses in each crate
+    --audit              check that unchecked uses are justified
+    --marker <text>      the justification marker (default: `SAFETY:`)
+    --format <format>    `text`, `json` or `sarif` (default: text)";
+
+const SCAN_USAGE: &str = "\
+usage: cargo reachability scan [--format <format>] <file>...
+
+Lists the `unreachable_static!()` sites that survived optimization in ELF
+objects, rlibs and executables, and the functions referencing them. With debug
+info (`-C debuginfo=line-tables-only` or more), the chain of functions inlined
+at each reference is shown too. Linked executables only record those functions
+when linked with `--emit-relocs`.
+
+options:
+    --format <format>    `text`, `json` or `sarif` (default: text)";
+
+const VERIFY_USAGE: &str = "\
+usage: cargo reachability verify [options] <file>...
+
+Lists the `unreachable_static!()` sites that still have live code in binaries
+built with the `warn` feature. Fails if there are any.
+
+options:
+    --crate <names>      comma separated crates whose sites are checked (default: all)
+    --format <format>    `text`, `json` or `sarif` (default: text)";
+
+fn main() {
+    let mut args = env::args_os().skip(1).peekable();
+    // `cargo reachability` runs us as `cargo-reachability reachability`
+    if args.peek().is_some_and(|arg| arg == "reachability") {
+        args.next();
+    }
+
+    let command = args.next().and_then(|arg| arg.into_string().ok());
+    let args: Vec<OsString> = args.collect();
+    let result = match command.as_deref() {
+        Some("matrix") => matrix(args),
+        Some("baseline") => baseline(args),
+        Some("bisect") => bisect(args),
+        Some("explain") => explain(args),
+        Some("list") => list_uses(args),
+        Some("scan") => scan(args),
+        Some("verify") => verify(args),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(true)
+        },
+        _ => Err(USAGE.into()),
+    };
+
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        },
+    }
+}
+
+/// `--flag value` pairs, with an empty value for switches.
+type Options = Vec<(String, String)>;
+
+/// Split `args` into options and everything after `--`. `switches` are the
+/// flags that don't take a value.
+fn options(args: Vec<OsString>, usage: &str, switches: &[&str]) -> Result<(Options, Vec<OsString>), String> {
+    parse_options(args, usage, switches, None)
+}
+
+/// Split `args` into options and the files they apply to: the arguments that
+/// aren't options, and everything after `--`. At least one file is required.
+fn files(args: Vec<OsString>, usage: &str, switches: &[&str]) -> Result<(Options, Vec<OsString>), String> {
+    let mut files = Vec::new();
+    let (options, rest) = parse_options(args, usage, switches, Some(&mut files))?;
+    files.extend(rest);
+    match files.is_empty() {
+        true => Err(usage.into()),
+        false => Ok((options, files)),
+    }
+}
+
+fn parse_options(args: Vec<OsString>, usage: &str, switches: &[&str], mut files: Option<&mut Vec<OsString>>) -> Result<(Options, Vec<OsString>), String> {
+    let mut options = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let arg = match (arg.into_string(), &mut files) {
+            (Ok(arg), _) => arg,
+            (Err(file), Some(files)) => {
+                files.push(file);
+                continue;
+            },
+            (Err(_), None) => return Err(usage.into()),
+        };
+        match &arg[..] {
+            "--" => break,
+            "-h" | "--help" => return Err(usage.into()),
+            flag if switches.contains(&flag) => options.push((flag.to_owned(), String::new())),
+            flag if flag.starts_with("--") => {
+                let value = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or_else(|| format!("error: {} requires a value\n\n{}", flag, usage))?;
+                options.push((flag.to_owned(), value));
+            },
+            _ => match &mut files {
+                Some(files) => files.push(arg.into()),
+                None => return Err(format!("error: unexpected argument `{}`\n\n{}", arg, usage)),
+            },
+        }
+    }
+    Ok((options, args.collect()))
+}
+
+fn list<T>(value: &str, parse: fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    value.split(',')
+        .map(|v| parse(v.trim()).ok_or_else(|| format!("error: unknown value `{}`", v)))
+        .collect()
+}
+
+fn format(value: &str) -> Result<Format, String> {
+    Format::parse(value).ok_or_else(|| format!("error: unknown value `{}`", value))
+}
+
+/// Print `report` in `format`, unless it is text and was printed already.
+fn print_report(format: Format, report: &Report) {
+    match format {
+        Format::Text => (),
+        Format::Json => println!("{:#}", report.to_json()),
+        Format::Sarif => println!("{:#}", report.to_sarif()),
+    }
+}
+
+fn matrix(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, cargo_args) = options(args, MATRIX_USAGE, &[])?;
+    let mut opt_levels = OptLevel::ALL.to_vec();
+    let mut ltos = Lto::ALL.to_vec();
+    let dir = env::current_dir().map_err(|e| format!("error: {}", e))?;
+    let mut expectations = match Config::find(&dir) {
+        Ok(config) => config.map(|(_, config)| config.matrix).unwrap_or_default(),
+        Err(e) => return Err(format!("error: {}", e)),
+    };
+    let mut junit = None;
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        match &flag[..] {
+            "--opt-level" => opt_levels = list(&value, OptLevel::parse)?,
+            "--lto" => ltos = list(&value, Lto::parse)?,
+            "--expect-fail" => expectations.fail.push(value),
+            "--expect-link" => expectations.links.push(ExpectLink::parse(&value)
+                .ok_or_else(|| format!("error: invalid `--expect-link` value `{}`\n\n{}", value, MATRIX_USAGE))?),
+            "--junit" => junit = Some(value),
+            "--format" => output = format(&value)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, MATRIX_USAGE)),
+        }
+    }
+
+    let profiles: Vec<Profile> = Profile::matrix().into_iter()
+        .filter(|p| p.debug_assertions || (opt_levels.contains(&p.opt_level) && ltos.contains(&p.lto)))
+        .collect();
+    let results = build_matrix(&profiles, &cargo_args)?;
+
+    let table = Table { results: &results, expectations: &expectations };
+    if let Some(path) = junit {
+        let xml = JUnit { results: &results, expectations: &expectations }.to_string();
+        std::fs::write(&path, xml).map_err(|e| format!("error: failed to write `{}`: {}", path, e))?;
+        eprintln!("wrote `{}`", path);
+    }
+    if output == Format::Text {
+        println!("{}", table);
+    }
+
+    let mut report = Report::new("matrix");
+    for result in &results {
+        for (target, outcome) in &result.targets {
+            let expect_link = expectations.link(&result.profile, target);
+            let finding = match outcome {
+                Outcome::LinkFailed(diagnostics) if expect_link => {
+                    for diagnostic in diagnostics.iter().filter(|d| d.site().is_some()) {
+                        if output == Format::Text {
+                            println!("{}: {}: {}", result.profile, target, diagnostic.site().unwrap());
+                        }
+                        report.findings.push(Finding::site(Rule::UnprovenSite, &diagnostic.symbol)
+                            .with("configuration", result.profile)
+                            .with("target", target));
+                    }
+                    continue;
+                },
+                Outcome::Linked if !expect_link => {
+                    Finding::new(Rule::UnexpectedLink, format!("`{}` linked but was expected to fail", target))
+                },
+                Outcome::Failed(error) => {
+                    if output == Format::Text {
+                        println!("{}: {}: {}", result.profile, target, error);
+                    }
+                    Finding::new(Rule::BuildError, format!("`{}` failed to build: {}", target, error))
+                },
+                _ => continue,
+            };
+            report.findings.push(finding.with("configuration", result.profile).with("target", target));
+        }
+    }
+    print_report(output, &report);
+
+    Ok(table.passed())
+}
+
+fn build_matrix(profiles: &[Profile], cargo_args: &[OsString]) -> Result<Vec<Results>, String> {
+    let mut results = Vec::new();
+    for (i, &profile) in profiles.iter().enumerate() {
+        eprintln!("[{}/{}] {}", i + 1, profiles.len(), profile);
+        let result = matrix::build(profile, cargo_args)
+            .map_err(|e| format!("error: failed to run cargo: {}", e))?;
+        results.push(result);
+    }
+    Ok(results)
+}
+
+fn baseline(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, cargo_args) = options(args, BASELINE_USAGE, &["--update"])?;
+    let mut path = BASELINE_FILE.to_owned();
+    let mut update = false;
+    let mut opt_levels = OptLevel::ALL.to_vec();
+    let mut ltos = Lto::ALL.to_vec();
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        match &flag[..] {
+            "--file" => path = value,
+            "--update" => update = true,
+            "--format" => output = format(&value)?,
+            "--opt-level" => opt_levels = list(&value, OptLevel::parse)?,
+            "--lto" => ltos = list(&value, Lto::parse)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, BASELINE_USAGE)),
+        }
+    }
+
+    let mut recorded = match std::fs::read_to_string(&path) {
+        Ok(contents) => Baseline::parse(&contents).map_err(|e| format!("error: invalid baseline `{}`: {}", path, e))?,
+        Err(e) if update && e.kind() == std::io::ErrorKind::NotFound => Baseline::default(),
+        Err(e) => return Err(format!("error: failed to read `{}`: {}", path, e)),
+    };
+
+    // Sites are never enforced with debug assertions, so skip that build
+    let profiles: Vec<Profile> = Profile::matrix().into_iter()
+        .filter(|p| !p.debug_assertions && opt_levels.contains(&p.opt_level) && ltos.contains(&p.lto))
+        .collect();
+    let rustc = matrix::rustc_version().ok();
+    let results = build_matrix(&profiles, &cargo_args)?;
+    let current = Baseline::from_results(rustc, &results).map_err(|e| format!("error: {}", e))?;
+
+    if update {
+        recorded.update(current);
+        std::fs::write(&path, recorded.to_string()).map_err(|e| format!("error: failed to write `{}`: {}", path, e))?;
+        eprintln!("wrote `{}`", path);
+        return Ok(true);
+    }
+
+    if recorded.rustc != current.rustc {
+        eprintln!("note: the baseline was recorded with rustc {}, building with rustc {}",
+            recorded.rustc.as_deref().unwrap_or("?"), current.rustc.as_deref().unwrap_or("?"));
+    }
+    let changes = recorded.diff(&current);
+    let mut report = Report::new("baseline");
+    for change in &changes {
+        if output == Format::Text {
+            println!("{}", change);
+        }
+        let finding = match change {
+            Change::Regressed { profile, key } => Finding::new(Rule::BaselineRegression, format!("{} is not in the baseline", key))
+                .at(Location::parse(baseline::key_file(key)))
+                .with("configuration", profile),
+            Change::Missing { profile } => Finding::new(Rule::BaselineRegression, format!("{} is not in the baseline", profile))
+                .with("configuration", profile),
+            Change::Proven { .. } => continue,
+        };
+        report.findings.push(finding);
+    }
+    print_report(output, &report);
+    let failed = changes.iter().filter(|c| !matches!(c, Change::Proven { .. })).count();
+    match (failed, changes.len()) {
+        (0, 0) => eprintln!("no changes from the baseline"),
+        (0, n) => eprintln!("{} site(s) are now proven, run with `--update` to record them", n),
+        (n, _) => eprintln!("{} site(s) or configuration(s) are not in the baseline", n),
+    }
+    Ok(failed == 0)
+}
+
+fn bisect(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, cargo_args) = options(args, BISECT_USAGE, &[])?;
+    let mut profile = Profile { opt_level: OptLevel::O3, lto: Lto::Off, debug_assertions: false };
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        let unknown = || format!("error: unknown value `{}`", value);
+        match &flag[..] {
+            "--opt-level" => profile.opt_level = OptLevel::parse(&value).ok_or_else(unknown)?,
+            "--lto" => profile.lto = Lto::parse(&value).ok_or_else(unknown)?,
+            "--format" => output = format(&value)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, BISECT_USAGE)),
+        }
+    }
+
+    let build = |args: &[OsString], envs: &[(&str, OsString)]| {
+        let cargo_args: Vec<_> = cargo_args.iter().chain(args).cloned().collect();
+        let build = matrix::build_with(profile, &cargo_args, envs)
+            .map_err(|e| format!("error: failed to run cargo: {}", e))?;
+        let mut failed = false;
+        for (target, outcome) in &build.results.targets {
+            match outcome {
+                Outcome::Linked => (),
+                Outcome::LinkFailed(_) => failed = true,
+                Outcome::Failed(error) => return Err(format!("error: {}: {}", target, error)),
+            }
+        }
+        Ok((failed, build.executables))
+    };
+
+    // Registering every site lists the ones that survive without failing
+    let (args, envs) = matrix::rustflags(None, &[], &["--cfg", "reachability=\"warn\"",
+        "--check-cfg", "cfg(reachability,values(\"static\",\"warn\",\"panic\",\"trap\",\"unchecked\"))"]);
+    eprintln!("listing the surviving sites");
+    let (failed, executables) = build(&args, &envs)?;
+    if failed {
+        return Err("error: the link fails with every site registered, so the sites can't be listed".into());
+    }
+    let mut sites = Vec::new();
+    for path in &executables {
+        let symbols = std::fs::read(path).and_then(|data| object::registered_sites(&data))
+            .map_err(|e| format!("error: failed to read `{}`: {}", path.display(), e))?;
+        sites.extend(symbols);
+    }
+    sites.sort_unstable();
+    sites.dedup();
+    if sites.is_empty() {
+        eprintln!("no `unreachable_static!()` sites survived");
+        print_report(output, &Report::new("bisect"));
+        return Ok(true);
+    }
+
+    let mut builds = 0;
+    let mut fails = |enforced: &[String]| {
+        builds += 1;
+        eprintln!("[{}] enforcing {} of {} sites", builds, enforced.len(), sites.len());
+        build(&[], &[(bisect::BISECT_VAR, bisect::var(enforced).into())]).map(|(failed, _)| failed)
+    };
+    if fails(&[])? {
+        return Err("error: the link fails with every site falling back, so it isn't failing on sites".into());
+    }
+    let found = bisect::search(&sites, fails)?;
+
+    let mut report = Report::new("bisect");
+    for set in &found {
+        if set.len() > 1 && output == Format::Text {
+            println!("{} sites fail only when enforced together:", set.len());
+        }
+        for symbol in set {
+            if output == Format::Text {
+                println!("{}\n", Diagnostic { symbol: symbol.clone(), functions: Vec::new() });
+            }
+            report.findings.push(Finding::site(Rule::UnprovenSite, symbol).with("configuration", profile).with("set", set.len()));
+        }
+    }
+    print_report(output, &report);
+    match found.iter().map(Vec::len).sum() {
+        0 => eprintln!("the link doesn't fail on any of the {} surviving sites", sites.len()),
+        1 => eprintln!("1 `unreachable_static!()` site fails the link"),
+        n => eprintln!("{} `unreachable_static!()` sites fail the link", n),
+    }
+    Ok(found.is_empty())
+}
+
+fn explain(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, cargo_args) = options(args, EXPLAIN_USAGE, &[])?;
+    let mut profile = Profile { opt_level: OptLevel::O3, lto: Lto::Off, debug_assertions: false };
+    let mut passes = "all".to_owned();
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        let unknown = || format!("error: unknown value `{}`", value);
+        match &flag[..] {
+            "--opt-level" => profile.opt_level = OptLevel::parse(&value).ok_or_else(unknown)?,
+            "--lto" => profile.lto = Lto::parse(&value).ok_or_else(unknown)?,
+            "--passes" => passes = value,
+            "--format" => output = format(&value)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, EXPLAIN_USAGE)),
+        }
+    }
+
+    // Remarks only have locations with debug info
+    let remark = format!("remark={}", passes);
+    let (args, envs) = matrix::rustflags(None, &[], &["-C", &remark, "-C", "debuginfo=line-tables-only"]);
+    let cargo_args: Vec<_> = cargo_args.into_iter().chain(args).collect();
+    let build = matrix::build_with(profile, &cargo_args, &envs)
+        .map_err(|e| format!("error: failed to run cargo: {}", e))?;
+    let remarks: Vec<Remark> = build.notes.iter().filter_map(|note| Remark::parse(note)).collect();
+
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut unnamed = false;
+    for (target, outcome) in &build.results.targets {
+        match outcome {
+            Outcome::Linked => (),
+            Outcome::LinkFailed(failed) if failed.is_empty() => unnamed = true,
+            Outcome::LinkFailed(failed) => {
+                for diagnostic in failed {
+                    match diagnostics.iter_mut().find(|d| d.symbol == diagnostic.symbol) {
+                        Some(d) => {
+                            d.functions.extend(diagnostic.functions.iter().cloned());
+                            d.functions.sort_unstable();
+                            d.functions.dedup();
+                        },
+                        None => diagnostics.push(diagnostic.clone()),
+                    }
+                }
+            },
+            Outcome::Failed(error) => return Err(format!("error: {}: {}", target, error)),
+        }
+    }
+    if unnamed {
+        eprintln!("warning: a link failed without naming its sites, `cargo reachability bisect` can find them");
+    }
+
+    let mut report = Report::new("explain");
+    for diagnostic in &diagnostics {
+        let explanation = remarks::explain(&remarks, &diagnostic.symbol, &diagnostic.functions);
+        let mut finding = Finding::site(Rule::UnprovenSite, &diagnostic.symbol)
+            .with("configuration", profile)
+            .with("functions", explanation.functions.join(", "));
+        for (i, remark) in explanation.remarks.iter().enumerate() {
+            finding = finding.with(&format!("missed.{}", i), remark);
+        }
+        report.findings.push(finding);
+        if output != Format::Text {
+            continue;
+        }
+        println!("{}", Diagnostic { symbol: diagnostic.symbol.clone(), functions: explanation.functions });
+        if explanation.remarks.is_empty() {
+            println!("  = note: no missed optimizations were reported");
+        }
+        for remark in explanation.remarks {
+            println!("  = missed: {}", remark);
+        }
+        println!();
+    }
+    print_report(output, &report);
+    match diagnostics.len() {
+        0 => eprintln!("no `unreachable_static!()` sites failed to link"),
+        1 => eprintln!("1 `unreachable_static!()` site was not eliminated"),
+        n => eprintln!("{} `unreachable_static!()` sites were not eliminated", n),
+    }
+    Ok(diagnostics.is_empty() && !unnamed)
+}
+
+fn list_uses(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, cargo_args) = options(args, LIST_USAGE, &["--deps", "--summary", "--audit"])?;
+    let mut deps = false;
+    let mut summary = false;
+    let mut audit = false;
+    let mut marker = inventory::SAFETY_MARKER.to_owned();
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        match &flag[..] {
+            "--deps" => deps = true,
+            "--summary" => summary = true,
+            "--audit" => audit = true,
+            "--marker" => marker = value,
+            "--format" => output = format(&value)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, LIST_USAGE)),
+        }
+    }
+
+    let packages = inventory::packages(&cargo_args, deps)
+        .map_err(|e| format!("error: failed to run `cargo metadata`: {}", e))?;
+    let mut scanned = Vec::new();
+    for package in packages {
+        // This crate's own uses are its definitions
+        if deps && package.name == "reachability" {
+            continue;
+        }
+        let uses = inventory::scan_package(&package)
+            .map_err(|e| format!("error: failed to read `{}`: {}", package.root.display(), e))?;
+        scanned.push((package, uses));
+    }
+    if audit {
+        return audit_uses(&scanned, &marker, output);
+    }
+
+    let mut totals = [0; 2];
+    let mut report = Report::new("list");
+    for (package, uses) in &scanned {
+        let counts = [Kind::Static, Kind::Unchecked].map(|kind| uses.iter().filter(|u| u.kind == kind).count());
+        for (total, count) in totals.iter_mut().zip(counts) {
+            *total += count;
+        }
+        if output != Format::Text {
+            report.findings.extend(uses.iter().map(|found| use_finding(package, found, match found.kind {
+                Kind::Static => Rule::StaticUse,
+                Kind::Unchecked => Rule::UncheckedUse,
+            }, format!("`{}` is {}", found.name, found.kind.as_str()))));
+            continue;
+        }
+        if uses.is_empty() {
+            continue;
+        }
+        println!("{}: {} static, {} unchecked", package.name, counts[0], counts[1]);
+        if summary {
+            continue;
+        }
+        for (&kind, count) in [Kind::Static, Kind::Unchecked].iter().zip(counts) {
+            if count > 0 {
+                println!("  {}:", kind.as_str());
+            }
+            for found in uses.iter().filter(|u| u.kind == kind) {
+                println!("    {}", found);
+            }
+        }
+    }
+    print_report(output, &report);
+    eprintln!("{} static and {} unchecked uses in {} package(s)", totals[0], totals[1], scanned.len());
+    Ok(true)
+}
+
+/// A finding at `found` in `package`, with its path relative to the current
+/// directory where it is below it.
+fn use_finding(package: &Package, found: &Use, rule: Rule, message: String) -> Finding {
+    let path = package.root.join(&found.file);
+    let path = env::current_dir().ok().and_then(|cwd| path.strip_prefix(cwd).ok().map(|p| p.to_owned())).unwrap_or(path);
+    Finding::new(rule, message)
+        .at(Location::new(&path.to_string_lossy(), found.line, found.column))
+        .with("package", &package.name)
+        .with("name", &found.name)
+}
+
+fn audit_uses(scanned: &[(Package, Vec<Use>)], marker: &str, output: Format) -> Result<bool, String> {
+    let mut checked = 0;
+    let mut report = Report::new("list");
+    for (package, uses) in scanned {
+        let mut source = (None, String::new());
+        for found in uses.iter().filter(|u| u.kind == Kind::Unchecked) {
+            let path = package.root.join(&found.file);
+            if source.0.as_ref() != Some(&path) {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("error: failed to read `{}`: {}", path.display(), e))?;
+                source = (Some(path.clone()), contents);
+            }
+            checked += 1;
+            if !inventory::justified(&source.1, found.line, marker) {
+                let message = format!("`{}` has no `{}` comment", found.name, marker);
+                report.findings.push(use_finding(package, found, Rule::UnjustifiedUnchecked, message));
+            }
+        }
+    }
+
+    if output == Format::Text {
+        for finding in &report.findings {
+            println!("{}", finding);
+        }
+    }
+    print_report(output, &report);
+    eprintln!("{} of {} unchecked uses are not justified", report.findings.len(), checked);
+    Ok(report.findings.is_empty())
+}
+
+fn scan(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, paths) = files(args, SCAN_USAGE, &[])?;
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        match &flag[..] {
+            "--format" => output = format(&value)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, SCAN_USAGE)),
+        }
+    }
+
+    let mut references = Vec::new();
+    for path in &paths {
+        let scanned = object::scan_file(path)
+            .map_err(|e| format!("error: failed to scan `{}`: {}", path.to_string_lossy(), e))?;
+        references.extend(scanned.into_iter().map(|reference| (path.to_string_lossy(), reference)));
+    }
+
+    // A site in both an rlib and the executable linking it is one site
+    let mut report = Report::new("scan");
+    for diagnostic in object::diagnostics(references.iter().map(|(_, reference)| reference)) {
+        let site: Vec<_> = references.iter().filter(|(_, r)| symbol::same_site(&r.symbol, &diagnostic.symbol)).collect();
+        let mut files = Vec::new();
+        for (path, _) in &site {
+            if !files.contains(path) {
+                files.push(path.clone());
+            }
+        }
+        report.findings.push(Finding::site(Rule::UnprovenSite, &diagnostic.symbol)
+            .with("object", files.join(", "))
+            .with("functions", diagnostic.functions.join(", ")));
+        if output != Format::Text {
+            continue;
+        }
+        println!("{}", diagnostic);
+        let mut chains = Vec::new();
+        for (_, reference) in site.iter().filter(|(_, r)| r.frames.len() > 1) {
+            let chain = dwarf::describe(&reference.frames);
+            if !chains.contains(&chain) {
+                println!("  = note: reached via {}", chain);
+                chains.push(chain);
+            }
+        }
+        let files: Vec<_> = files.iter().map(|file| format!("`{}`", file)).collect();
+        println!("  = note: in {}\n", files.join(", "));
+    }
+    print_report(output, &report);
+
+    match report.findings.len() {
+        0 => eprintln!("no `unreachable_static!()` sites found"),
+        1 => eprintln!("1 `unreachable_static!()` site was not eliminated"),
+        n => eprintln!("{} `unreachable_static!()` sites were not eliminated", n),
+    }
+    Ok(report.findings.is_empty())
+}
+
+fn verify(args: Vec<OsString>) -> Result<bool, String> {
+    let (options, paths) = files(args, VERIFY_USAGE, &[])?;
+    let mut scope = Scope::all();
+    let mut output = Format::Text;
+    for (flag, value) in options {
+        match &flag[..] {
+            "--crate" => scope = Scope::parse(&value),
+            "--format" => output = format(&value)?,
+            _ => return Err(format!("error: unknown option `{}`\n\n{}", flag, VERIFY_USAGE)),
+        }
+    }
+
+    let mut report = Report::new("verify");
+    for path in &paths {
+        let symbols = std::fs::read(path).and_then(|data| object::registered_sites(&data))
+            .map_err(|e| format!("error: failed to read `{}`: {}", path.to_string_lossy(), e))?;
+        for symbol in symbols.into_iter().filter(|symbol| scope.includes(symbol)) {
+            report.findings.push(Finding::site(Rule::UnprovenSite, &symbol).with("binary", path.to_string_lossy()));
+            if output == Format::Text {
+                println!("{}\n  = note: in `{}`\n", Diagnostic { symbol, functions: Vec::new() }, path.to_string_lossy());
+            }
+        }
+    }
+    print_report(output, &report);
+
+    let sites = report.findings.len();
+    match sites {
+        0 => eprintln!("no `unreachable_static!()` sites survived"),
+        1 => eprintln!("1 `unreachable_static!()` site was not eliminated"),
+        n => eprintln!("{} `unreachable_static!()` sites were not eliminated", n),
+    }
+    Ok(sites == 0)
+}
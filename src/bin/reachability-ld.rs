@@ -0,0 +1,140 @@
+//! Linker wrapper that explains `unreachable_static!()` link failures.
+//!
+//! See `reachability::ld` for details, `reachability::fallback` for
+//! `REACHABILITY_FALLBACK` and `reachability::scope` for
+//! `REACHABILITY_ENFORCE`.
+
+use std::{env, fs};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::process::{self, Command, Output};
+use reachability::{fallback, ld, object};
+use reachability::scope::{self, Scope};
+
+fn main() {
+    let linker = env::var_os("REACHABILITY_LINKER").unwrap_or_else(|| "cc".into());
+    let fallback = env::var_os("REACHABILITY_FALLBACK").is_some_and(|v| !v.is_empty() && v != "0");
+    let mut scope = Scope::all();
+    let args = scope.take_link_args(env::args_os().skip(1).collect()).unwrap_or_else(|e| exit(&linker, e));
+    if let Ok(crates) = env::var(scope::SCOPE_VAR) {
+        scope.enforce(&crates);
+    }
+    let args = match ld::is_cc_driver(&linker) {
+        true => ld::cc_args(args),
+        false => Ok(args),
+    };
+    let mut args = args.unwrap_or_else(|e| exit(&linker, e));
+    let mut output = link(&linker, &args);
+
+    let mut diagnostics = Vec::new();
+    if !output.status.success() {
+        // lld stops after 20 errors, which would leave sites out of the
+        // diagnostics and the fallbacks
+        if let Some(arg) = ld::error_limit_arg(&linker, &log(&output)) {
+            args.push(arg.into());
+            output = link(&linker, &args);
+        }
+        diagnostics = ld::diagnostics(&log(&output));
+    }
+
+    let unenforced: Vec<ld::Diagnostic> = diagnostics.iter()
+        .filter(|d| fallback || !scope.includes(&d.symbol))
+        .cloned()
+        .collect();
+    if !unenforced.is_empty() {
+        match link_fallback(&linker, &args, &unenforced) {
+            Ok(fallback) => {
+                output = fallback;
+                diagnostics = match output.status.success() {
+                    true => Vec::new(),
+                    false => ld::diagnostics(&log(&output)),
+                };
+            },
+            Err(e) => eprintln!("error: failed to link `unreachable_static!()` fallbacks: {}\n", e),
+        }
+    }
+
+    if output.status.success() && !scope.is_all() {
+        if let Err(e) = check_registered(&args, &scope) {
+            let _ = io::stdout().write_all(&output.stdout);
+            let _ = io::stderr().write_all(&output.stderr);
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+
+    let _ = io::stdout().write_all(&output.stdout);
+    if !output.status.success() {
+        for diagnostic in &diagnostics {
+            eprintln!("{}\n", diagnostic);
+        }
+    }
+    let _ = io::stderr().write_all(&output.stderr);
+
+    process::exit(output.status.code().unwrap_or(1));
+}
+
+fn link(linker: &OsStr, args: &[OsString]) -> Output {
+    Command::new(linker).args(args).output().unwrap_or_else(|e| exit(linker, e))
+}
+
+fn log(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned() + &String::from_utf8_lossy(&output.stdout)
+}
+
+fn exit(linker: &OsStr, e: io::Error) -> ! {
+    eprintln!("error: failed to run linker `{}`: {}", linker.to_string_lossy(), e);
+    process::exit(1);
+}
+
+/// Write the report of unproven sites and link again with them defined.
+fn link_fallback(linker: &OsStr, args: &[OsString], diagnostics: &[ld::Diagnostic]) -> io::Result<Output> {
+    let output = ld::output(args)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no output file"))?;
+    let report = fallback::report_path(&output);
+    let mut contents = String::new();
+    for diagnostic in diagnostics {
+        contents.push_str(&format!("{}\n\n", diagnostic));
+    }
+    fs::write(&report, contents)?;
+
+    let symbols: Vec<String> = diagnostics.iter().map(|d| d.symbol.clone()).collect();
+    let mut object = report.clone().into_os_string();
+    object.push(".o");
+    fs::write(&object, fallback::object(fallback::machine(args)?, &symbols)?)?;
+
+    // First, so linkers that scan archives once still resolve the fallback
+    let mut fallback_args = vec![object];
+    fallback_args.extend_from_slice(args);
+    let output = link(linker, &fallback_args);
+    if output.status.success() {
+        eprintln!(
+            "warning: {} `unreachable_static!()` site(s) were not eliminated and will panic if reached, see `{}`",
+            diagnostics.len(), report.display(),
+        );
+    }
+    Ok(output)
+}
+
+/// Fail on `warn` sites of enforced crates that survived in the output.
+fn check_registered(args: &[OsString], scope: &Scope) -> io::Result<()> {
+    let output = match ld::output(args)? {
+        Some(output) => output,
+        None => return Ok(()),
+    };
+    let sites: Vec<String> = object::registered_sites(&fs::read(&output)?)?
+        .into_iter()
+        .filter(|symbol| scope.includes(symbol))
+        .collect();
+    if sites.is_empty() {
+        return Ok(());
+    }
+
+    for symbol in &sites {
+        eprintln!("{}\n", ld::Diagnostic { symbol: symbol.clone(), functions: Vec::new() });
+    }
+    let _ = fs::remove_file(&output);
+    Err(io::Error::other(format!(
+        "{} `unreachable_static!()` site(s) of enforced crates were not eliminated", sites.len(),
+    )))
+}
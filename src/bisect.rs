@@ -0,0 +1,146 @@
+//! Bisection of failing links for `cargo reachability bisect`.
+//!
+//! When a link fails without naming the sites that survived (the linker's
+//! output is lost, or it stops after the first few undefined symbols), the
+//! failing sites can still be found by rebuilding with only some of them
+//! enforced. [`BISECT_VAR`] is read when each site is compiled: if it is set,
+//! only the sites whose link name hashes (see [`hash`]) it lists reference
+//! their undefined symbol, and the others panic like `std::unreachable!()`.
+//! Sites are only affected where they would otherwise be enforced at link
+//! time, so the modes and features still decide the rest.
+//!
+//! `cargo reachability bisect` first builds with `--cfg reachability="warn"`
+//! to list the surviving sites from the binaries' registries (see
+//! [`crate::registry`]), then narrows them down with [`search`].
+
+/// The environment variable listing the enforced sites, as comma separated
+/// hexadecimal [`hash`]es, read when the sites are compiled.
+pub const BISECT_VAR: &str = "REACHABILITY_BISECT";
+
+/// The 64-bit FNV-1a hash of a site's link name.
+pub const fn hash(symbol: &str) -> u64 {
+    let bytes = symbol.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Whether the site with link name `symbol` is enforced given the value of
+/// [`BISECT_VAR`]. Everything is enforced without it.
+#[doc(hidden)]
+pub const fn enforced(hashes: Option<&str>, symbol: &str) -> bool {
+    let hashes = match hashes {
+        Some(hashes) => hashes.as_bytes(),
+        None => return true,
+    };
+    let symbol = hash(symbol);
+    let mut value = 0u64;
+    let mut digits = 0;
+    let mut i = 0;
+    while i <= hashes.len() {
+        let digit = if i < hashes.len() { hashes[i] } else { b',' };
+        match digit {
+            b'0'..=b'9' => value = value << 4 | (digit - b'0') as u64,
+            b'a'..=b'f' => value = value << 4 | (digit - b'a' + 10) as u64,
+            b'A'..=b'F' => value = value << 4 | (digit - b'A' + 10) as u64,
+            b',' => {
+                if digits > 0 && value == symbol {
+                    return true;
+                }
+                value = 0;
+                digits = 0;
+                i += 1;
+                continue;
+            },
+            _ => (),
+        }
+        digits += 1;
+        i += 1;
+    }
+    false
+}
+
+/// The value of [`BISECT_VAR`] enforcing `symbols`.
+#[cfg(any(test, feature = "std"))]
+pub fn var<S: AsRef<str>>(symbols: &[S]) -> String {
+    let hashes: Vec<_> = symbols.iter().map(|s| format!("{:x}", hash(s.as_ref()))).collect();
+    hashes.join(",")
+}
+
+/// Find the sites among `sites` that fail the link. `fails` builds with the
+/// given sites enforced and the rest falling back, and returns whether the
+/// link failed.
+///
+/// Returns one minimal failing set after another, each removed from the
+/// enforced sites before looking for the next, until the remaining sites
+/// link. Sites usually fail on their own, giving sets of one, but falling
+/// back changes what gets inlined, so a set can need several sites enforced
+/// together.
+#[cfg(any(test, feature = "std"))]
+pub fn search<T: Clone, E>(sites: &[T], mut fails: impl FnMut(&[T]) -> Result<bool, E>) -> Result<Vec<Vec<T>>, E> {
+    let mut fails = |indices: &[usize]| fails(&indices.iter().map(|&i| sites[i].clone()).collect::<Vec<_>>());
+    let mut remaining: Vec<usize> = (0..sites.len()).collect();
+    let mut found = Vec::new();
+    while !remaining.is_empty() && fails(&remaining)? {
+        let set = minimize(&[], &remaining, &mut fails)?;
+        remaining.retain(|i| !set.contains(i));
+        found.push(set.into_iter().map(|i| sites[i].clone()).collect());
+    }
+    Ok(found)
+}
+
+/// A minimal subset of `sites` that fails when enforced along with
+/// `context`, given that `context` alone links and all of `sites` with it
+/// fails.
+#[cfg(any(test, feature = "std"))]
+fn minimize<E>(context: &[usize], sites: &[usize], fails: &mut impl FnMut(&[usize]) -> Result<bool, E>) -> Result<Vec<usize>, E> {
+    if sites.len() <= 1 {
+        return Ok(sites.to_vec());
+    }
+    let (a, b) = sites.split_at(sites.len() / 2);
+    let with = |extra: &[usize]| [context, extra].concat();
+    if fails(&with(a))? {
+        return minimize(context, a, fails);
+    }
+    if fails(&with(b))? {
+        return minimize(context, b, fails);
+    }
+    // Neither half fails alone, so the set needs sites from both
+    let a = minimize(&with(b), a, fails)?;
+    let b = minimize(&with(&a), b, fails)?;
+    Ok([a, b].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes() {
+        let a = "___unreachable_static___@app@src/main.rs:3:5";
+        let b = "___unreachable_static___@app@src/main.rs:4:5";
+        assert_eq!(hash(""), 0xcbf2_9ce4_8422_2325);
+        assert!(enforced(None, a));
+        assert!(!enforced(Some(""), a));
+        assert!(enforced(Some(&var(&[b, a])), a));
+        assert!(enforced(Some(&var(&[a]).to_uppercase()), a));
+        assert!(!enforced(Some(&var(&[b])), a));
+    }
+
+    #[test]
+    fn bisect() {
+        // 3 fails alone, 5 and 9 only together
+        let mut builds = 0;
+        let found = search(&(0..12).collect::<Vec<_>>(), |enforced: &[i32]| {
+            builds += 1;
+            Ok::<_, ()>(enforced.contains(&3) || (enforced.contains(&5) && enforced.contains(&9)))
+        }).unwrap();
+        assert_eq!(found, [vec![3], vec![5, 9]]);
+        assert!(builds < 20, "{}", builds);
+    }
+}
@@ -0,0 +1,681 @@
+//! `reachability.toml` project configuration, applied from build scripts.
+//!
+//! Rather than every binary wiring up the `static` feature itself, a
+//! workspace describes its sites once in a `reachability.toml` next to its
+//! root `Cargo.toml`, and each crate's `build.rs` applies it:
+//!
+//! ```toml
+//! # The `reachability_unchecked` cfg, see `unreachable_unchecked!`
+//! unchecked = "checked"
+//! # Crates enforced when linking through `reachability-ld`, see `reachability::scope`
+//! enforce = ["app", "parser"]
+//! # Sites that fall back to `unreachable!()` instead, as `<file>[:<line>[:<column>]]`
+//! allow = ["src/legacy.rs:120"]
+//!
+//! [toolchain]
+//! # Warn when building with another rustc or opt-level than the proofs were made with
+//! rustc = "1.80"
+//! opt-level = ["3"]
+//!
+//! # The `reachability` cfg of crates calling `configure()`, see `unreachable_static!`,
+//! # from the first entry applying to the build
+//! [[mode]]
+//! mode = "static"
+//! profile = ["release"]
+//!
+//! # Sites that only fall back in some builds, by location or by message
+//! [[allow]]
+//! site = "src/codec.rs:88"
+//! opt-level = ["s", "z"]
+//!
+//! [[allow]]
+//! name = "bad header"
+//! profile = ["dist"]
+//!
+//! # Expected link failures for `cargo reachability matrix`
+//! [matrix]
+//! expect-fail = ["fail"]
+//!
+//! # Targets that only link in some configurations
+//! [[matrix.expect-link]]
+//! target = "inlined"
+//! opt-level = [2, 3, "s", "z"]
+//! lto = ["thin", "fat"]
+//! ```
+//!
+//! A plain `mode = "static"` applies to every build, including the
+//! unoptimized ones where `cargo build` and `cargo test` can't prove sites, so
+//! `[[mode]]` entries scope it to optimized profiles or opt-levels instead.
+//!
+//! Both forms of `allow` can be mixed in one file, as `allow = [...]` and
+//! `[[allow]]` tables are collected into the same list. An entry applies to
+//! every build unless it lists the `opt-level`s or profiles it applies to.
+//! Profiles are named by their directory under `target`, so `dev` is
+//! `debug`.
+//!
+//! The `[matrix]` table is only read by `cargo reachability matrix`, see
+//! [`crate::matrix::Expectations`].
+//!
+//! ```no_run
+//! // In `main()` of build.rs, with `reachability` and its `std` feature as a
+//! // build dependency
+//! reachability::build::configure();
+//! ```
+//!
+//! The file is found by searching from the package being built up to the
+//! filesystem root. `enforce` and `allow` are passed as linker arguments, so
+//! they only take effect with `reachability-ld` as the linker, set either as
+//! the target's `linker` or with `-C linker=` in rustflags; other linkers get
+//! a warning instead.
+//!
+//! The build script reruns when any of the searched paths changes, including
+//! ones that don't exist yet. Cargo counts a missing path as changed, so it
+//! reruns on every build until a `reachability.toml` is found.
+
+use std::{env, fmt, fs, io};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::matrix::{ExpectLink, Expectations, Lto, OptLevel};
+use crate::scope::{ALLOW_ARG, ALLOW_NAME_ARG, ENFORCE_ARG, SCOPE_VAR};
+use crate::toml::{self, Value};
+
+/// The name of the configuration file.
+pub const CONFIG_FILE: &str = "reachability.toml";
+
+/// The values of the `reachability` cfg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Static,
+    Warn,
+    Panic,
+    Trap,
+    Unchecked,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 5] = [Mode::Static, Mode::Warn, Mode::Panic, Mode::Trap, Mode::Unchecked];
+
+    /// The value of the `reachability` cfg.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Static => "static",
+            Mode::Warn => "warn",
+            Mode::Panic => "panic",
+            Mode::Trap => "trap",
+            Mode::Unchecked => "unchecked",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Mode::ALL.iter().copied().find(|m| m.as_str() == s)
+    }
+}
+
+/// A parsed `reachability.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// The `reachability` cfg, from the first entry applying to the build.
+    pub mode: Vec<ModeEntry>,
+    /// The `reachability_unchecked` cfg, `true` for `"checked"`.
+    pub checked: Option<bool>,
+    /// Crates to enforce, or `None` for every crate.
+    pub enforce: Option<Vec<String>>,
+    /// Sites that are never enforced.
+    pub allow: Vec<Allow>,
+    pub toolchain: Toolchain,
+    /// The link expectations of `cargo reachability matrix`.
+    pub matrix: Expectations,
+}
+
+/// A `mode` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeEntry {
+    pub mode: Mode,
+    /// The opt-levels the entry applies to, or every opt-level if empty.
+    pub opt_levels: Vec<OptLevel>,
+    /// The profiles the entry applies to, or every profile if empty.
+    pub profiles: Vec<String>,
+}
+
+impl ModeEntry {
+    /// An entry for `mode` in every build.
+    pub fn new(mode: Mode) -> Self {
+        ModeEntry { mode, opt_levels: Vec::new(), profiles: Vec::new() }
+    }
+
+    /// Whether the entry applies to a build described by `env`.
+    pub fn applies(&self, env: &BuildEnv) -> bool {
+        applies(&self.opt_levels, &self.profiles, env)
+    }
+
+    fn parse(value: &Value) -> Result<Self, String> {
+        let table = match value {
+            Value::Table(table) => table,
+            _ => return Err("`mode` entries must be tables".into()),
+        };
+        let mut entry = None;
+        let mut opt_levels = Vec::new();
+        let mut profiles = Vec::new();
+        for (key, v) in table {
+            match &key[..] {
+                "mode" => entry = Some(mode(v)?),
+                "opt-level" => opt_levels = self::opt_levels(key, v)?,
+                "profile" => profiles = strings(key, v)?,
+                _ => return Err(format!("unknown key `mode.{}`", key)),
+            }
+        }
+        let mode = entry.ok_or("`mode` entries need a `mode`")?;
+        Ok(ModeEntry { mode, opt_levels, profiles })
+    }
+}
+
+/// An `allow` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allow {
+    pub site: SiteId,
+    /// The opt-levels the entry applies to, or every opt-level if empty.
+    pub opt_levels: Vec<OptLevel>,
+    /// The profiles the entry applies to, or every profile if empty.
+    pub profiles: Vec<String>,
+}
+
+impl Allow {
+    /// An entry for `site` in every build.
+    pub fn new(site: SiteId) -> Self {
+        Allow { site, opt_levels: Vec::new(), profiles: Vec::new() }
+    }
+
+    /// Whether the entry applies to a build described by `env`.
+    pub fn applies(&self, env: &BuildEnv) -> bool {
+        applies(&self.opt_levels, &self.profiles, env)
+    }
+
+    fn parse(value: &Value) -> Result<Self, String> {
+        let table = match value {
+            Value::String(site) => return Ok(Allow::new(SiteId::Location(site.clone()))),
+            Value::Table(table) => table,
+            _ => return Err("`allow` entries must be strings or tables".into()),
+        };
+        let mut site = None;
+        let mut allow = Allow::new(SiteId::Name(String::new()));
+        for (key, v) in table {
+            match &key[..] {
+                "site" => site = Some(SiteId::Location(string(key, v)?.to_owned())),
+                "name" => site = Some(SiteId::Name(string(key, v)?.to_owned())),
+                "opt-level" => allow.opt_levels = opt_levels(key, v)?,
+                "profile" => allow.profiles = strings(key, v)?,
+                _ => return Err(format!("unknown key `allow.{}`", key)),
+            }
+        }
+        allow.site = site.ok_or("`allow` entries need a `site` or `name`")?;
+        Ok(allow)
+    }
+}
+
+/// How an `allow` entry identifies its sites.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SiteId {
+    /// `<file>[:<line>[:<column>]]`, see `reachability::symbol::Site::matches`.
+    Location(String),
+    /// The message of the site, such as `bad header` for
+    /// `unreachable_static!("bad header")`.
+    Name(String),
+}
+
+/// The `[toolchain]` the sites are expected to be proven with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Toolchain {
+    /// A prefix of the `rustc` version, such as `1.80` or `1.80.1`.
+    pub rustc: Option<String>,
+    pub opt_levels: Vec<OptLevel>,
+}
+
+/// A configuration error, with the file it was found in.
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for Error { }
+
+impl Config {
+    /// Parse the contents of a `reachability.toml`.
+    pub fn parse(s: &str) -> Result<Config, String> {
+        let value = toml::parse(s).map_err(|e| e.to_string())?;
+        let mut config = Config::default();
+        for (key, v) in value.as_table() {
+            match &key[..] {
+                "mode" => match v {
+                    Value::Array(entries) => {
+                        for entry in entries {
+                            config.mode.push(ModeEntry::parse(entry)?);
+                        }
+                    },
+                    v => config.mode.push(ModeEntry::new(mode(v)?)),
+                },
+                "unchecked" => config.checked = Some(match string(key, v)? {
+                    "checked" => true,
+                    "unchecked" => false,
+                    u => return Err(format!("unknown `unchecked` value `{}`", u)),
+                }),
+                "enforce" => config.enforce = Some(strings(key, v)?),
+                "allow" => match v {
+                    Value::Array(entries) => {
+                        for entry in entries {
+                            config.allow.push(Allow::parse(entry)?);
+                        }
+                    },
+                    _ => return Err("`allow` must be an array".into()),
+                },
+                "toolchain" => {
+                    for (key, v) in v.as_table() {
+                        match &key[..] {
+                            "rustc" => config.toolchain.rustc = Some(string(key, v)?.to_owned()),
+                            "opt-level" => config.toolchain.opt_levels = opt_levels("toolchain.opt-level", v)?,
+                            _ => return Err(format!("unknown key `toolchain.{}`", key)),
+                        }
+                    }
+                },
+                "matrix" => {
+                    for (key, v) in v.as_table() {
+                        match &key[..] {
+                            "expect-fail" => config.matrix.fail = strings("matrix.expect-fail", v)?,
+                            "expect-link" => match v {
+                                Value::Array(entries) => {
+                                    for entry in entries {
+                                        config.matrix.links.push(expect_link(entry)?);
+                                    }
+                                },
+                                _ => return Err("`matrix.expect-link` must be an array".into()),
+                            },
+                            _ => return Err(format!("unknown key `matrix.{}`", key)),
+                        }
+                    }
+                },
+                _ => return Err(format!("unknown key `{}`", key)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Find and parse the `reachability.toml` in `dir` or its ancestors.
+    pub fn find(dir: &Path) -> Result<Option<(PathBuf, Config)>, Error> {
+        for dir in dir.ancestors() {
+            let path = dir.join(CONFIG_FILE);
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error { path, message: e.to_string() }),
+            };
+            return match Config::parse(&contents) {
+                Ok(config) => Ok(Some((path, config))),
+                Err(message) => Err(Error { path, message }),
+            };
+        }
+        Ok(None)
+    }
+
+    /// The build script instructions applying the configuration to a build
+    /// described by `env`.
+    pub fn instructions(&self, env: &BuildEnv) -> Vec<String> {
+        let mut lines = vec![
+            format!("cargo:rustc-check-cfg=cfg(reachability, values({}))",
+                Mode::ALL.iter().map(|m| format!("\"{}\"", m.as_str())).collect::<Vec<_>>().join(", ")),
+            "cargo:rustc-check-cfg=cfg(reachability_unchecked, values(\"checked\", \"unchecked\"))".to_owned(),
+        ];
+        if let Some(entry) = self.mode.iter().find(|entry| entry.applies(env)) {
+            lines.push(format!("cargo:rustc-cfg=reachability=\"{}\"", entry.mode.as_str()));
+        }
+        if let Some(checked) = self.checked {
+            lines.push(format!("cargo:rustc-cfg=reachability_unchecked=\"{}\"", if checked { "checked" } else { "unchecked" }));
+        }
+
+        let allow: Vec<&Allow> = self.allow.iter().filter(|allow| allow.applies(env)).collect();
+        if self.enforce.is_some() || !allow.is_empty() {
+            if env.linker_is_wrapper() {
+                if let Some(crates) = &self.enforce {
+                    lines.push(format!("cargo:rustc-link-arg={}{}", ENFORCE_ARG, crates.join(",")));
+                }
+                for allow in allow {
+                    lines.push(match &allow.site {
+                        SiteId::Location(site) => format!("cargo:rustc-link-arg={}{}", ALLOW_ARG, site),
+                        SiteId::Name(name) => format!("cargo:rustc-link-arg={}{}", ALLOW_NAME_ARG, name),
+                    });
+                }
+            } else {
+                lines.push(format!("cargo:warning={}: `enforce` and `allow` need `reachability-ld` as the linker", CONFIG_FILE));
+            }
+        }
+
+        if let (Some(expected), Some(version)) = (&self.toolchain.rustc, &env.rustc_version) {
+            let matches = version.strip_prefix(&expected[..])
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '-', ' ']));
+            if !matches {
+                lines.push(format!("cargo:warning={}: expected rustc {} but building with {}, sites may not be proven", CONFIG_FILE, expected, version));
+            }
+        }
+        if let Some(opt_level) = env.opt_level {
+            if !self.toolchain.opt_levels.is_empty() && !self.toolchain.opt_levels.contains(&opt_level) {
+                lines.push(format!("cargo:warning={}: expected opt-level {} but building with {}, sites may not be proven", CONFIG_FILE,
+                    self.toolchain.opt_levels.iter().map(|o| o.as_str()).collect::<Vec<_>>().join(" or "), opt_level.as_str()));
+            }
+        }
+        lines
+    }
+}
+
+/// Whether an entry scoped to `opt_levels` and `profiles`, each empty for
+/// every build, applies to a build described by `env`.
+fn applies(opt_levels: &[OptLevel], profiles: &[String], env: &BuildEnv) -> bool {
+    let opt_level = opt_levels.is_empty() || env.opt_level.is_some_and(|o| opt_levels.contains(&o));
+    let profile = profiles.is_empty() || env.profile.as_ref().is_some_and(|p| profiles.contains(p));
+    opt_level && profile
+}
+
+fn mode(value: &Value) -> Result<Mode, String> {
+    string("mode", value).and_then(|m| Mode::parse(m).ok_or_else(|| format!("unknown mode `{}`", m)))
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("`{}` must be a string", key))
+}
+
+fn strings(key: &str, value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::Array(values) => values.iter().map(|v| string(key, v).map(String::from)).collect(),
+        _ => Err(format!("`{}` must be an array of strings", key)),
+    }
+}
+
+fn opt_levels(key: &str, value: &Value) -> Result<Vec<OptLevel>, String> {
+    let level = |v: &Value| {
+        let s = match v {
+            Value::Integer(i) => i.to_string(),
+            Value::String(s) => s.clone(),
+            _ => String::new(),
+        };
+        OptLevel::parse(&s).ok_or_else(|| format!("`{}` must be opt-levels", key))
+    };
+    match value {
+        Value::Array(values) => values.iter().map(level).collect(),
+        v => Ok(vec![level(v)?]),
+    }
+}
+
+fn ltos(key: &str, value: &Value) -> Result<Vec<Lto>, String> {
+    let lto = |v: &Value| v.as_str().and_then(Lto::parse).ok_or_else(|| format!("`{}` must be LTO modes", key));
+    match value {
+        Value::Array(values) => values.iter().map(lto).collect(),
+        v => Ok(vec![lto(v)?]),
+    }
+}
+
+fn expect_link(value: &Value) -> Result<ExpectLink, String> {
+    let table = match value {
+        Value::Table(table) => table,
+        _ => return Err("`matrix.expect-link` entries must be tables".into()),
+    };
+    let mut target = None;
+    let mut link = ExpectLink { target: String::new(), opt_levels: Vec::new(), ltos: Vec::new() };
+    for (key, v) in table {
+        match &key[..] {
+            "target" => target = Some(string("matrix.expect-link.target", v)?.to_owned()),
+            "opt-level" => link.opt_levels = opt_levels("matrix.expect-link.opt-level", v)?,
+            "lto" => link.ltos = ltos("matrix.expect-link.lto", v)?,
+            _ => return Err(format!("unknown key `matrix.expect-link.{}`", key)),
+        }
+    }
+    link.target = target.ok_or("`matrix.expect-link` entries need a `target`")?;
+    Ok(link)
+}
+
+/// What a build script knows about the build it's part of.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildEnv {
+    /// The version of `rustc`, such as `1.80.1` or `1.82.0-nightly`.
+    pub rustc_version: Option<String>,
+    pub opt_level: Option<OptLevel>,
+    /// The name of the profile's directory under `target`.
+    pub profile: Option<String>,
+    /// The linker set for the target, if any.
+    pub linker: Option<PathBuf>,
+}
+
+impl BuildEnv {
+    /// Read the environment Cargo runs build scripts with.
+    pub fn from_env() -> Self {
+        let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let rustc_version = Command::new(rustc).arg("--version").output().ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .and_then(|version| Some(version.split_whitespace().nth(1)?.to_owned()));
+        BuildEnv {
+            rustc_version,
+            opt_level: env::var("OPT_LEVEL").ok().and_then(|o| OptLevel::parse(&o)),
+            // `OUT_DIR` is `<profile>/build/<package>-<hash>/out`
+            profile: env::var_os("OUT_DIR")
+                .and_then(|out| Some(Path::new(&out).ancestors().nth(3)?.file_name()?.to_str()?.to_owned())),
+            linker: env::var("CARGO_ENCODED_RUSTFLAGS").ok().and_then(|flags| rustflags_linker(&flags))
+                .or_else(|| env::var_os("RUSTC_LINKER").map(PathBuf::from)),
+        }
+    }
+
+    fn linker_is_wrapper(&self) -> bool {
+        self.linker.as_ref()
+            .and_then(|linker| linker.file_stem())
+            .is_some_and(|name| name == "reachability-ld")
+    }
+}
+
+/// The linker set by `-C linker=` in `CARGO_ENCODED_RUSTFLAGS`, which
+/// overrides the target's `linker` as rustc takes the last one.
+fn rustflags_linker(flags: &str) -> Option<PathBuf> {
+    let mut linker = None;
+    let mut flags = flags.split('\x1f').filter(|flag| !flag.is_empty());
+    while let Some(flag) = flags.next() {
+        let codegen = match flag {
+            "-C" | "--codegen" => flags.next(),
+            _ => flag.strip_prefix("-C").or_else(|| flag.strip_prefix("--codegen=")),
+        };
+        if let Some(path) = codegen.and_then(|c| c.strip_prefix("linker=")) {
+            linker = Some(PathBuf::from(path));
+        }
+    }
+    linker
+}
+
+/// Apply the workspace's `reachability.toml` to the package being built.
+/// Call from `build.rs`.
+///
+/// # Panics
+///
+/// If the configuration can't be read or is invalid.
+pub fn configure() {
+    let dir = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from)
+        .expect("`reachability::build::configure()` must be called from a build script");
+    let config = match Config::find(&dir) {
+        Ok(Some((path, config))) => {
+            rerun_if_changed(&dir, Some(&path));
+            config
+        },
+        Ok(None) => {
+            rerun_if_changed(&dir, None);
+            Config::default()
+        },
+        Err(e) => panic!("invalid {}", e),
+    };
+    for var in ["CARGO_ENCODED_RUSTFLAGS", "RUSTC_LINKER", SCOPE_VAR] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+    for line in config.instructions(&BuildEnv::from_env()) {
+        println!("{}", line);
+    }
+}
+
+/// Rerun the build script if a `reachability.toml` appears, changes, or goes
+/// away anywhere `Config::find()` looks, up to the one it found.
+fn rerun_if_changed(dir: &Path, found: Option<&Path>) {
+    for dir in dir.ancestors() {
+        let path = dir.join(CONFIG_FILE);
+        println!("cargo:rerun-if-changed={}", path.display());
+        if Some(path.as_path()) == found {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Profile;
+
+    const CONFIG: &str = r#"
+        unchecked = "checked"
+        enforce = ["app", "parser"]
+        allow = ["src/legacy.rs:120"]
+
+        [toolchain]
+        rustc = "1.80"
+        opt-level = [3, "s"]
+
+        [[mode]]
+        mode = "static"
+        opt-level = [3, "s", "z"]
+
+        [[mode]]
+        mode = "panic"
+
+        [[allow]]
+        site = "src/codec.rs:88"
+        opt-level = ["s", "z"]
+
+        [[allow]]
+        name = "bad header"
+        profile = ["dist"]
+
+        [matrix]
+        expect-fail = ["fail"]
+
+        [[matrix.expect-link]]
+        target = "inlined"
+        opt-level = [2, 3, "s", "z"]
+        lto = ["thin", "fat"]
+    "#;
+
+    #[test]
+    fn config() {
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config, Config {
+            mode: vec![
+                ModeEntry { mode: Mode::Static, opt_levels: vec![OptLevel::O3, OptLevel::Os, OptLevel::Oz], profiles: Vec::new() },
+                ModeEntry::new(Mode::Panic),
+            ],
+            checked: Some(true),
+            enforce: Some(vec!["app".into(), "parser".into()]),
+            allow: vec![
+                Allow::new(SiteId::Location("src/legacy.rs:120".into())),
+                Allow { site: SiteId::Location("src/codec.rs:88".into()), opt_levels: vec![OptLevel::Os, OptLevel::Oz], profiles: Vec::new() },
+                Allow { site: SiteId::Name("bad header".into()), opt_levels: Vec::new(), profiles: vec!["dist".into()] },
+            ],
+            toolchain: Toolchain { rustc: Some("1.80".into()), opt_levels: vec![OptLevel::O3, OptLevel::Os] },
+            matrix: Expectations { fail: vec!["fail".into()], links: vec![ExpectLink {
+                target: "inlined".into(),
+                opt_levels: vec![OptLevel::O2, OptLevel::O3, OptLevel::Os, OptLevel::Oz],
+                ltos: vec![Lto::Thin, Lto::Fat],
+            }] },
+        });
+
+        assert_eq!(Config::parse("mode = \"static\"").unwrap().mode, [ModeEntry::new(Mode::Static)]);
+        assert_eq!(Config::parse("mode = \"fast\""), Err("unknown mode `fast`".into()));
+        assert_eq!(Config::parse("[[mode]]\nprofile = [\"release\"]"), Err("`mode` entries need a `mode`".into()));
+        assert_eq!(Config::parse("[toolchain]\nlto = true"), Err("unknown key `toolchain.lto`".into()));
+        assert_eq!(Config::parse("allow = \"x\""), Err("`allow` must be an array".into()));
+        assert_eq!(Config::parse("[[allow]]\nopt-level = 3"), Err("`allow` entries need a `site` or `name`".into()));
+        assert_eq!(Config::parse("[[matrix.expect-link]]\nlto = \"on\""), Err("`matrix.expect-link.lto` must be LTO modes".into()));
+        assert_eq!(Config::parse("[[matrix.expect-link]]\nlto = \"fat\""), Err("`matrix.expect-link` entries need a `target`".into()));
+    }
+
+    #[test]
+    fn release() {
+        // The rules `release.nix` spelled out before reading the file
+        let config = Config::parse(include_str!("../reachability.toml")).unwrap();
+        for profile in Profile::matrix() {
+            let optimized = profile.opt_level != OptLevel::O0;
+            let opt2 = optimized && profile.opt_level != OptLevel::O1;
+            let lto = match profile.lto {
+                Lto::Off => false,
+                Lto::Thin => optimized,
+                Lto::Fat => opt2,
+            };
+            for (target, expected) in [("opt1", optimized), ("assert", optimized), ("ops", optimized), ("unwrap", optimized),
+                ("opt2", opt2), ("lto", lto), ("fail", false), ("fail-black-box", false)] {
+                assert_eq!(config.matrix.link(&profile, target), expected || profile.debug_assertions, "{} {}", target, profile);
+            }
+        }
+    }
+
+    #[test]
+    fn instructions() {
+        let config = Config::parse(CONFIG).unwrap();
+        let env = BuildEnv {
+            rustc_version: Some("1.80.1".into()),
+            opt_level: Some(OptLevel::O3),
+            profile: Some("release".into()),
+            linker: Some("/usr/bin/reachability-ld".into()),
+        };
+        assert_eq!(config.instructions(&env)[2..], [
+            "cargo:rustc-cfg=reachability=\"static\"",
+            "cargo:rustc-cfg=reachability_unchecked=\"checked\"",
+            "cargo:rustc-link-arg=--reachability-enforce=app,parser",
+            "cargo:rustc-link-arg=--reachability-allow=src/legacy.rs:120",
+        ]);
+
+        let env = BuildEnv {
+            opt_level: Some(OptLevel::Oz),
+            profile: Some("dist".into()),
+            linker: Some("/usr/bin/reachability-ld".into()),
+            ..env
+        };
+        assert_eq!(config.instructions(&env)[5..], [
+            "cargo:rustc-link-arg=--reachability-allow=src/legacy.rs:120",
+            "cargo:rustc-link-arg=--reachability-allow=src/codec.rs:88",
+            "cargo:rustc-link-arg=--reachability-allow-name=bad header",
+            "cargo:warning=reachability.toml: expected opt-level 3 or s but building with z, sites may not be proven",
+        ]);
+
+        let env = BuildEnv { rustc_version: Some("1.800.0".into()), linker: None, ..env };
+        assert_eq!(config.instructions(&env)[4..], [
+            "cargo:warning=reachability.toml: `enforce` and `allow` need `reachability-ld` as the linker",
+            "cargo:warning=reachability.toml: expected rustc 1.80 but building with 1.800.0, sites may not be proven",
+            "cargo:warning=reachability.toml: expected opt-level 3 or s but building with z, sites may not be proven",
+        ]);
+        assert!(Config::default().instructions(&env)[0].starts_with("cargo:rustc-check-cfg=cfg(reachability, values(\"static\""));
+    }
+
+    #[test]
+    fn debug_mode() {
+        let debug = BuildEnv { opt_level: Some(OptLevel::O0), profile: Some("debug".into()), ..BuildEnv::default() };
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config.instructions(&debug)[2], "cargo:rustc-cfg=reachability=\"panic\"");
+
+        let config = Config::parse("[[mode]]\nmode = \"static\"\nprofile = [\"release\"]").unwrap();
+        assert!(!config.instructions(&debug).iter().any(|line| line.starts_with("cargo:rustc-cfg=reachability=")));
+        let release = BuildEnv { opt_level: Some(OptLevel::O3), profile: Some("release".into()), ..BuildEnv::default() };
+        assert_eq!(config.instructions(&release)[2], "cargo:rustc-cfg=reachability=\"static\"");
+    }
+
+    #[test]
+    fn rustflags_linker() {
+        assert_eq!(super::rustflags_linker("-C\x1flinker=/usr/bin/reachability-ld"), Some("/usr/bin/reachability-ld".into()));
+        assert_eq!(super::rustflags_linker("-Clinker=cc\x1f--cfg\x1ffoo\x1f--codegen=linker=reachability-ld"), Some("reachability-ld".into()));
+        assert_eq!(super::rustflags_linker("-C\x1fopt-level=3\x1f--codegen\x1flinker=clang"), Some("clang".into()));
+        assert_eq!(super::rustflags_linker("--cfg\x1flinker=cc"), None);
+        assert_eq!(super::rustflags_linker(""), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! Demangling of the Rust symbol names that show up in linker output.
+
+use std::borrow::Cow;
+
+/// Demangle a legacy (`_ZN...E`) Rust symbol, dropping its trailing hash.
+///
+/// Already demangled names only have their hash removed, and anything else
+/// (including `v0` symbols) is returned unchanged.
+///
+/// ```
+/// use reachability::demangle::demangle;
+///
+/// assert_eq!(demangle("_ZN3app3net12parse_header17h0123456789abcdefE"), "app::net::parse_header");
+/// assert_eq!(demangle("_ZN42_$LT$app..Header$u20$as$u20$app..Parse$GT$5parse17h0123456789abcdefE"), "<app::Header as app::Parse>::parse");
+/// assert_eq!(demangle("app::main::h0123456789abcdef"), "app::main");
+/// ```
+pub fn demangle(symbol: &str) -> Cow<'_, str> {
+    let mangled = symbol.strip_prefix("__ZN").or_else(|| symbol.strip_prefix("_ZN"));
+    match mangled.and_then(legacy) {
+        Some(demangled) => Cow::Owned(demangled),
+        None => Cow::Borrowed(strip_hash(symbol)),
+    }
+}
+
+fn legacy(mut s: &str) -> Option<String> {
+    let mut path = Vec::new();
+    while !s.starts_with('E') {
+        let digits = s.find(|c: char| !c.is_ascii_digit())?;
+        let len: usize = s[..digits].parse().ok()?;
+        let end = digits.checked_add(len)?;
+        path.push(s.get(digits..end)?);
+        s = &s[end..];
+    }
+
+    if path.last().is_some_and(|ident| is_hash(ident)) {
+        path.pop();
+    }
+
+    let mut demangled = String::new();
+    for (i, ident) in path.into_iter().enumerate() {
+        if i > 0 {
+            demangled.push_str("::");
+        }
+        let ident = if ident.starts_with("_$") { &ident[1..] } else { ident };
+        unescape(ident, &mut demangled)?;
+    }
+    Some(demangled)
+}
+
+fn unescape(mut ident: &str, out: &mut String) -> Option<()> {
+    while !ident.is_empty() {
+        if let Some(rest) = ident.strip_prefix("..") {
+            out.push_str("::");
+            ident = rest;
+        } else if let Some(rest) = ident.strip_prefix('$') {
+            let end = rest.find('$')?;
+            let escape = &rest[..end];
+            out.push(match escape {
+                "SP" => '@',
+                "BP" => '*',
+                "RF" => '&',
+                "LT" => '<',
+                "GT" => '>',
+                "LP" => '(',
+                "RP" => ')',
+                "C" => ',',
+                _ => {
+                    let hex = escape.strip_prefix('u')?;
+                    core::char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+                },
+            });
+            ident = &rest[end + 1..];
+        } else {
+            let end = ident.find(['$', '.']).unwrap_or(ident.len());
+            let end = if end == 0 { 1 } else { end };
+            out.push_str(&ident[..end]);
+            ident = &ident[end..];
+        }
+    }
+    Some(())
+}
+
+fn strip_hash(symbol: &str) -> &str {
+    match symbol.rfind("::") {
+        Some(i) if is_hash(&symbol[i + 2..]) => &symbol[..i],
+        _ => symbol,
+    }
+}
+
+fn is_hash(ident: &str) -> bool {
+    ident.len() == 17 && ident.starts_with('h') && ident[1..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::demangle;
+
+    #[test]
+    fn escapes() {
+        assert_eq!(demangle("_ZN4core3ptr85drop_in_place$LT$std..rt..lang_start$LT$$LP$$RP$$GT$..$u7b$$u7b$closure$u7d$$u7d$$GT$17h0123456789abcdefE"),
+            "core::ptr::drop_in_place<std::rt::lang_start<()>::{{closure}}>");
+        assert_eq!(demangle("_ZN4test9h01234567E"), "test::h01234567");
+        assert_eq!(demangle("_ZN4test"), "_ZN4test");
+        assert_eq!(demangle("main"), "main");
+        assert_eq!(demangle("_ZN18446744073709551615aE"), "_ZN18446744073709551615aE");
+    }
+}
@@ -0,0 +1,886 @@
+//! DWARF line tables and inlining for `cargo reachability scan`.
+//!
+//! `unreachable_static!()` is only eliminated once its caller has been
+//! inlined far enough for the compiler to see why it can't be reached, so a
+//! surviving site usually sits in a chain of inlined functions. The chain is
+//! recovered from the `DW_TAG_inlined_subroutine` entries covering the
+//! reference's address, with the source location of each call taken from
+//! their `DW_AT_call_*` attributes and the innermost location from the line
+//! table. DWARF versions 2 to 5 are supported. Split DWARF
+//! (`-C split-debuginfo=unpacked`) moves the inlining into `.dwo` files, which
+//! aren't read, leaving only the function containing the reference.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use crate::demangle::demangle;
+
+/// A code address: the index of the section containing it in relocatable
+/// objects, or 0 in linked files, and the offset in that section or the
+/// virtual address.
+pub(crate) type Address = (u32, u64);
+
+/// One function of an inline chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The demangled name of the function, if known.
+    pub function: Option<String>,
+    /// The source file of the location in the function.
+    pub file: Option<String>,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Renders `function` at `file:line:column`.
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`", self.function.as_deref().unwrap_or("<unknown>"))?;
+        if let Some(file) = &self.file {
+            write!(f, " at {}:{}", file, self.line)?;
+            if self.column != 0 {
+                write!(f, ":{}", self.column)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describe an inline chain, innermost frame first, as "`inner` inlined into
+/// `outer` at file:line".
+///
+/// The innermost frame's location is the site itself, so it isn't repeated.
+pub fn describe(frames: &[Frame]) -> String {
+    let mut description = String::new();
+    for (i, frame) in frames.iter().enumerate() {
+        match i {
+            0 => description.push_str(&format!("`{}`", frame.function.as_deref().unwrap_or("<unknown>"))),
+            _ => description.push_str(&format!("{} inlined into {}", if i > 1 { "," } else { "" }, frame)),
+        }
+    }
+    description
+}
+
+/// A debug section, with the section each relocated address in it refers to.
+#[derive(Debug, Default)]
+pub(crate) struct DebugSection<'a> {
+    pub data: Cow<'a, [u8]>,
+    pub targets: HashMap<usize, u32>,
+}
+
+/// The debug sections of an object.
+#[derive(Debug, Default)]
+pub(crate) struct Dwarf<'a> {
+    pub little_endian: bool,
+    pub info: DebugSection<'a>,
+    pub abbrev: DebugSection<'a>,
+    pub str: DebugSection<'a>,
+    pub line_str: DebugSection<'a>,
+    pub line: DebugSection<'a>,
+    pub ranges: DebugSection<'a>,
+    pub rnglists: DebugSection<'a>,
+    pub addr: DebugSection<'a>,
+    pub str_offsets: DebugSection<'a>,
+}
+
+/// The names of the sections in [`Dwarf`], in field order.
+pub(crate) const SECTIONS: [&str; 9] = [
+    ".debug_info", ".debug_abbrev", ".debug_str", ".debug_line_str", ".debug_line",
+    ".debug_ranges", ".debug_rnglists", ".debug_addr", ".debug_str_offsets",
+];
+
+const DW_TAG_SUBPROGRAM: u64 = 0x2e;
+const DW_TAG_INLINED_SUBROUTINE: u64 = 0x1d;
+const DW_TAG_COMPILE_UNIT: u64 = 0x11;
+const DW_TAG_PARTIAL_UNIT: u64 = 0x3c;
+
+const DW_AT_NAME: u64 = 0x03;
+const DW_AT_STMT_LIST: u64 = 0x10;
+const DW_AT_LOW_PC: u64 = 0x11;
+const DW_AT_HIGH_PC: u64 = 0x12;
+const DW_AT_COMP_DIR: u64 = 0x1b;
+const DW_AT_ABSTRACT_ORIGIN: u64 = 0x31;
+const DW_AT_SPECIFICATION: u64 = 0x47;
+const DW_AT_RANGES: u64 = 0x55;
+const DW_AT_CALL_COLUMN: u64 = 0x57;
+const DW_AT_CALL_FILE: u64 = 0x58;
+const DW_AT_CALL_LINE: u64 = 0x59;
+const DW_AT_LINKAGE_NAME: u64 = 0x6e;
+const DW_AT_STR_OFFSETS_BASE: u64 = 0x72;
+const DW_AT_ADDR_BASE: u64 = 0x73;
+const DW_AT_RNGLISTS_BASE: u64 = 0x74;
+const DW_AT_MIPS_LINKAGE_NAME: u64 = 0x2007;
+
+/// An attribute value, with only the distinctions needed here.
+#[derive(Debug, Clone)]
+enum Value {
+    Address(Address),
+    AddressIndex(u64),
+    Constant(u64),
+    String(String),
+    StringIndex(u64),
+    StringOffset(u64),
+    LineStringOffset(u64),
+    Reference(usize),
+    SectionOffset(u64),
+    RangeListIndex(u64),
+    Other,
+}
+
+/// The attributes of an entry.
+type Attributes = Vec<(u64, Value)>;
+
+/// A file index, line and column.
+type Location = (u64, u32, u32);
+
+#[derive(Debug)]
+struct Abbrev {
+    tag: u64,
+    children: bool,
+    /// Attribute, form and `DW_FORM_implicit_const` value.
+    attributes: Vec<(u64, u64, i64)>,
+}
+
+/// A unit header, and the bases set by its root entry.
+#[derive(Debug, Clone, Copy, Default)]
+struct Unit {
+    offset: usize,
+    end: usize,
+    entries: usize,
+    version: u16,
+    address_size: u8,
+    offset_size: u8,
+    abbrev_offset: u64,
+    str_offsets_base: u64,
+    addr_base: u64,
+    rnglists_base: u64,
+}
+
+impl Unit {
+    /// Set the bases from the attributes of the unit's root entry.
+    fn set_bases(&mut self, attributes: &[(u64, Value)]) {
+        for (name, value) in attributes {
+            match (*name, value) {
+                (DW_AT_STR_OFFSETS_BASE, &Value::SectionOffset(base)) => self.str_offsets_base = base,
+                (DW_AT_ADDR_BASE, &Value::SectionOffset(base)) => self.addr_base = base,
+                (DW_AT_RNGLISTS_BASE, &Value::SectionOffset(base)) => self.rnglists_base = base,
+                _ => (),
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    section: &'a DebugSection<'a>,
+    pos: usize,
+    little_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(section: &'a DebugSection<'a>, pos: usize, little_endian: bool) -> Self {
+        Reader { section, pos, little_endian }
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let data: &'a [u8] = &self.section.data;
+        let bytes = data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn uint(&mut self, n: usize) -> Option<u64> {
+        let bytes = self.bytes(n)?;
+        let fold = |v: u64, b: &u8| v << 8 | *b as u64;
+        match self.little_endian {
+            true => Some(bytes.iter().rev().fold(0, fold)),
+            false => Some(bytes.iter().fold(0, fold)),
+        }
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.uint(1).map(|v| v as u8)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.uint(2).map(|v| v as u16)
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    fn cstr(&mut self) -> Option<String> {
+        let data: &'a [u8] = &self.section.data;
+        let s = data.get(self.pos..)?;
+        let end = s.iter().position(|&b| b == 0)?;
+        self.pos += end + 1;
+        Some(String::from_utf8_lossy(&s[..end]).into_owned())
+    }
+
+    fn address(&mut self, size: u8) -> Option<Address> {
+        let section = self.section.targets.get(&self.pos).copied().unwrap_or(0);
+        Some((section, self.uint(size as usize)?))
+    }
+
+    /// A unit length, and the size of offsets in the unit.
+    fn length(&mut self) -> Option<(usize, u8)> {
+        match self.uint(4)? {
+            0xffff_ffff => Some((self.uint(8)? as usize, 8)),
+            length => Some((length as usize, 4)),
+        }
+    }
+}
+
+/// An address range.
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    start: Address,
+    end: u64,
+}
+
+impl Range {
+    fn contains(&self, address: Address) -> bool {
+        self.start.0 == address.0 && self.start.1 <= address.1 && address.1 < self.end
+    }
+}
+
+/// A line table.
+#[derive(Debug, Default)]
+struct Lines {
+    files: Vec<Option<String>>,
+    /// The index of the first file.
+    file_base: u64,
+    /// Address, file, line and column, with `None` ending a sequence.
+    rows: Vec<Option<(Address, u64, u32, u32)>>,
+}
+
+impl Lines {
+    fn file(&self, index: u64) -> Option<String> {
+        self.files.get(index.checked_sub(self.file_base)? as usize).cloned().flatten()
+    }
+
+    fn find(&self, address: Address) -> Option<Location> {
+        let mut found = None;
+        for rows in self.rows.windows(2) {
+            if let [Some((start, file, line, column)), Some((end, ..))] = rows {
+                if start.0 == address.0 && start.1 <= address.1 && address.1 < end.1 {
+                    found = Some((*file, *line, *column));
+                }
+            }
+        }
+        found
+    }
+}
+
+impl<'a> Dwarf<'a> {
+    fn reader(&'a self, section: &'a DebugSection<'a>, pos: usize) -> Reader<'a> {
+        Reader::new(section, pos, self.little_endian)
+    }
+
+    /// The inline chain at each of `addresses`, innermost frame first.
+    pub fn frames(&self, addresses: &[Address]) -> Vec<Vec<Frame>> {
+        let mut frames = vec![Vec::new(); addresses.len()];
+        let mut abbrevs = HashMap::new();
+        let mut offset = 0;
+        while offset < self.info.data.len() {
+            let unit = match self.unit(offset) {
+                Some(unit) => unit,
+                None => break,
+            };
+            offset = unit.end;
+            abbrevs.entry(unit.abbrev_offset).or_insert_with(|| self.abbrevs(unit.abbrev_offset).unwrap_or_default());
+            let _ = self.unit_frames(unit, &abbrevs[&unit.abbrev_offset], addresses, &mut frames);
+        }
+        frames
+    }
+
+    fn unit(&self, offset: usize) -> Option<Unit> {
+        let mut r = self.reader(&self.info, offset);
+        let (length, offset_size) = r.length()?;
+        let end = r.pos.checked_add(length)?;
+        let version = r.u16()?;
+        let mut unit = Unit { offset, end, version, offset_size, ..Unit::default() };
+        if version >= 5 {
+            let unit_type = r.u8()?;
+            unit.address_size = r.u8()?;
+            unit.abbrev_offset = r.uint(offset_size as usize)?;
+            match unit_type {
+                // Compile and partial units
+                1 | 3 => (),
+                // Skeleton and split compile units
+                4 | 5 => {
+                    r.uint(8)?;
+                },
+                // Type units
+                _ => unit.entries = end,
+            }
+        } else {
+            unit.abbrev_offset = r.uint(offset_size as usize)?;
+            unit.address_size = r.u8()?;
+        }
+        if unit.entries == 0 {
+            unit.entries = r.pos;
+        }
+        Some(unit)
+    }
+
+    fn abbrevs(&self, offset: u64) -> Option<HashMap<u64, Abbrev>> {
+        let mut abbrevs = HashMap::new();
+        let mut r = self.reader(&self.abbrev, offset as usize);
+        loop {
+            let code = r.uleb()?;
+            if code == 0 {
+                return Some(abbrevs);
+            }
+            let tag = r.uleb()?;
+            let children = r.u8()? != 0;
+            let mut attributes = Vec::new();
+            loop {
+                let (name, form) = (r.uleb()?, r.uleb()?);
+                if name == 0 && form == 0 {
+                    break;
+                }
+                let implicit = if form == 0x21 { r.sleb()? } else { 0 };
+                attributes.push((name, form, implicit));
+            }
+            abbrevs.insert(code, Abbrev { tag, children, attributes });
+        }
+    }
+
+    /// Read an attribute value of `form`.
+    fn value(&self, r: &mut Reader, unit: &Unit, form: u64, implicit: i64) -> Option<Value> {
+        let offset_size = unit.offset_size as usize;
+        let value = match form {
+            0x01 => Value::Address(r.address(unit.address_size)?),
+            0x03 => { let n = r.u16()?; r.bytes(n as usize)?; Value::Other },
+            0x04 => { let n = r.uint(4)?; r.bytes(n as usize)?; Value::Other },
+            0x09 | 0x18 => { let n = r.uleb()?; r.bytes(n as usize)?; Value::Other },
+            0x0a => { let n = r.u8()?; r.bytes(n as usize)?; Value::Other },
+            0x05 => Value::Constant(r.uint(2)?),
+            0x06 => Value::Constant(r.uint(4)?),
+            0x07 => Value::Constant(r.uint(8)?),
+            0x0b | 0x0c => Value::Constant(r.uint(1)?),
+            0x0f => Value::Constant(r.uleb()?),
+            0x0d => Value::Constant(r.sleb()? as u64),
+            0x19 => Value::Constant(1),
+            0x21 => Value::Constant(implicit as u64),
+            0x1e => { r.bytes(16)?; Value::Other },
+            0x08 => Value::String(r.cstr()?),
+            0x0e => Value::StringOffset(r.uint(offset_size)?),
+            0x1f => Value::LineStringOffset(r.uint(offset_size)?),
+            0x1a => Value::StringIndex(r.uleb()?),
+            0x25..=0x28 => Value::StringIndex(r.uint(form as usize - 0x24)?),
+            0x1b => Value::AddressIndex(r.uleb()?),
+            0x29..=0x2c => Value::AddressIndex(r.uint(form as usize - 0x28)?),
+            0x10 => Value::Reference(r.uint(if unit.version <= 2 { unit.address_size as usize } else { offset_size })? as usize),
+            0x11 => Value::Reference(unit.offset.wrapping_add(r.uint(1)? as usize)),
+            0x12 => Value::Reference(unit.offset.wrapping_add(r.uint(2)? as usize)),
+            0x13 => Value::Reference(unit.offset.wrapping_add(r.uint(4)? as usize)),
+            0x14 => Value::Reference(unit.offset.wrapping_add(r.uint(8)? as usize)),
+            0x15 => Value::Reference(unit.offset.wrapping_add(r.uleb()? as usize)),
+            0x17 => Value::SectionOffset(r.uint(offset_size)?),
+            0x1d => { r.uint(offset_size)?; Value::Other },
+            0x1c => { r.uint(4)?; Value::Other },
+            0x20 | 0x24 => { r.uint(8)?; Value::Other },
+            0x22 => { r.uleb()?; Value::Other },
+            0x23 => Value::RangeListIndex(r.uleb()?),
+            0x16 => {
+                let form = r.uleb()?;
+                return self.value(r, unit, form, implicit);
+            },
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Read the entry at `r`, returning its abbreviation and attributes.
+    fn entry<'b>(&self, r: &mut Reader, unit: &Unit, abbrevs: &'b HashMap<u64, Abbrev>) -> Option<Option<(&'b Abbrev, Attributes)>> {
+        let code = r.uleb()?;
+        if code == 0 {
+            return Some(None);
+        }
+        let abbrev = abbrevs.get(&code)?;
+        let mut attributes = Vec::with_capacity(abbrev.attributes.len());
+        for &(name, form, implicit) in &abbrev.attributes {
+            attributes.push((name, self.value(r, unit, form, implicit)?));
+        }
+        Some(Some((abbrev, attributes)))
+    }
+
+    fn string(&self, unit: &Unit, value: &Value) -> Option<String> {
+        match *value {
+            Value::String(ref s) => Some(s.clone()),
+            Value::StringOffset(offset) => self.reader(&self.str, offset as usize).cstr(),
+            Value::LineStringOffset(offset) => self.reader(&self.line_str, offset as usize).cstr(),
+            Value::StringIndex(index) => {
+                let size = unit.offset_size as u64;
+                let offset = self.reader(&self.str_offsets, unit.str_offsets_base.wrapping_add(index.wrapping_mul(size)) as usize).uint(size as usize)?;
+                self.reader(&self.str, offset as usize).cstr()
+            },
+            _ => None,
+        }
+    }
+
+    fn address(&self, unit: &Unit, value: &Value) -> Option<Address> {
+        match *value {
+            Value::Address(address) => Some(address),
+            Value::AddressIndex(index) => self.indexed_address(unit, index),
+            _ => None,
+        }
+    }
+
+    fn indexed_address(&self, unit: &Unit, index: u64) -> Option<Address> {
+        let size = unit.address_size;
+        self.reader(&self.addr, unit.addr_base.wrapping_add(index.wrapping_mul(size as u64)) as usize).address(size)
+    }
+
+    /// The address ranges of an entry with `attributes`.
+    fn ranges(&self, unit: &Unit, base: Option<Address>, attributes: &[(u64, Value)]) -> Option<Vec<Range>> {
+        let attribute = |name| attributes.iter().find(|(n, _)| *n == name).map(|(_, v)| v);
+        if let Some(low) = attribute(DW_AT_LOW_PC).and_then(|v| self.address(unit, v)) {
+            let end = match attribute(DW_AT_HIGH_PC)? {
+                Value::Constant(size) => low.1.wrapping_add(*size),
+                high => self.address(unit, high)?.1,
+            };
+            return Some(vec![Range { start: low, end }]);
+        }
+
+        let base = base.unwrap_or((0, 0));
+        let mut ranges = Vec::new();
+        match *attribute(DW_AT_RANGES)? {
+            Value::SectionOffset(offset) if unit.version < 5 => {
+                let mut r = self.reader(&self.ranges, offset as usize);
+                let mut base = base;
+                let max = u64::MAX.checked_shr(64u32.checked_sub(8 * unit.address_size as u32)?)?;
+                loop {
+                    let (start, end) = (r.address(unit.address_size)?, r.address(unit.address_size)?);
+                    if start.1 == 0 && end.1 == 0 {
+                        break;
+                    } else if start.1 == max {
+                        base = end;
+                    } else {
+                        let section = if start.0 != 0 { start.0 } else { base.0 };
+                        ranges.push(Range { start: (section, base.1.wrapping_add(start.1)), end: base.1.wrapping_add(end.1) });
+                    }
+                }
+            },
+            Value::SectionOffset(offset) => self.range_list(unit, base, offset as usize, &mut ranges)?,
+            Value::RangeListIndex(index) => {
+                let size = unit.offset_size as u64;
+                let offset = self.reader(&self.rnglists, unit.rnglists_base.wrapping_add(index.wrapping_mul(size)) as usize).uint(size as usize)?;
+                self.range_list(unit, base, unit.rnglists_base.wrapping_add(offset) as usize, &mut ranges)?;
+            },
+            _ => return None,
+        }
+        Some(ranges)
+    }
+
+    /// Read a DWARF 5 range list.
+    fn range_list(&self, unit: &Unit, mut base: Address, offset: usize, ranges: &mut Vec<Range>) -> Option<()> {
+        let mut r = self.reader(&self.rnglists, offset);
+        loop {
+            match r.u8()? {
+                0 => return Some(()),
+                1 => base = self.indexed_address(unit, r.uleb()?)?,
+                2 => {
+                    let start = self.indexed_address(unit, r.uleb()?)?;
+                    let end = self.indexed_address(unit, r.uleb()?)?;
+                    ranges.push(Range { start, end: end.1 });
+                },
+                3 => {
+                    let start = self.indexed_address(unit, r.uleb()?)?;
+                    ranges.push(Range { start, end: start.1.wrapping_add(r.uleb()?) });
+                },
+                4 => {
+                    let (start, end) = (r.uleb()?, r.uleb()?);
+                    ranges.push(Range { start: (base.0, base.1.wrapping_add(start)), end: base.1.wrapping_add(end) });
+                },
+                5 => base = r.address(unit.address_size)?,
+                6 => {
+                    let start = r.address(unit.address_size)?;
+                    ranges.push(Range { start, end: r.address(unit.address_size)?.1 });
+                },
+                7 => {
+                    let start = r.address(unit.address_size)?;
+                    ranges.push(Range { start, end: start.1.wrapping_add(r.uleb()?) });
+                },
+                _ => return None,
+            }
+        }
+    }
+
+    /// Find the chains of `addresses` covered by `unit`.
+    fn unit_frames(&self, mut unit: Unit, abbrevs: &HashMap<u64, Abbrev>, addresses: &[Address], frames: &mut [Vec<Frame>]) -> Option<()> {
+        let mut r = self.reader(&self.info, unit.entries);
+        let (root, attributes) = self.entry(&mut r, &unit, abbrevs)??;
+        if root.tag != DW_TAG_COMPILE_UNIT && root.tag != DW_TAG_PARTIAL_UNIT {
+            return None;
+        }
+        unit.set_bases(&attributes);
+        let attribute = |name| attributes.iter().find(|(n, _)| *n == name).map(|(_, v)| v);
+        let base = attribute(DW_AT_LOW_PC).and_then(|v| self.address(&unit, v));
+        if let Some(ranges) = self.ranges(&unit, base, &attributes) {
+            if !addresses.iter().any(|&a| ranges.iter().any(|r| r.contains(a))) {
+                return Some(());
+            }
+        }
+        let comp_dir = attribute(DW_AT_COMP_DIR).and_then(|v| self.string(&unit, v));
+        let lines = match attribute(DW_AT_STMT_LIST) {
+            Some(&Value::SectionOffset(offset)) | Some(&Value::Constant(offset)) =>
+                self.lines(&unit, offset as usize, comp_dir.as_deref()).unwrap_or_default(),
+            _ => Lines::default(),
+        };
+
+        // The containing subprogram and inlined subroutines of each address,
+        // outermost first, with the depth of each entry
+        let mut chains: Vec<Vec<(usize, Frame, Option<Location>)>> = vec![Vec::new(); addresses.len()];
+        let mut best = vec![0; addresses.len()];
+        let mut depth = root.children as usize;
+        while depth > 0 && r.pos < unit.end {
+            let (abbrev, attributes) = match self.entry(&mut r, &unit, abbrevs)? {
+                Some(entry) => entry,
+                None => {
+                    depth -= 1;
+                    continue;
+                },
+            };
+            if abbrev.tag == DW_TAG_SUBPROGRAM || abbrev.tag == DW_TAG_INLINED_SUBROUTINE {
+                let ranges = self.ranges(&unit, base, &attributes).unwrap_or_default();
+                for (i, &address) in addresses.iter().enumerate() {
+                    if !ranges.iter().any(|r| r.contains(address)) {
+                        continue;
+                    }
+                    let attribute = |name| attributes.iter().find(|(n, _)| *n == name).map(|(_, v)| v);
+                    let constant = |name| match attribute(name) {
+                        Some(&Value::Constant(v)) => Some(v),
+                        _ => None,
+                    };
+                    let call = constant(DW_AT_CALL_FILE)
+                        .map(|file| (file, constant(DW_AT_CALL_LINE).unwrap_or(0) as u32, constant(DW_AT_CALL_COLUMN).unwrap_or(0) as u32));
+                    let function = self.function(&unit, &attributes, abbrevs, 4);
+                    let frame = Frame { function, file: None, line: 0, column: 0 };
+                    chains[i].retain(|(d, ..)| *d < depth);
+                    chains[i].push((depth, frame, call));
+                    if chains[i].len() >= best[i] {
+                        best[i] = chains[i].len();
+                        frames[i] = self.chain(&chains[i], &lines, address);
+                    }
+                }
+            }
+            if abbrev.children {
+                depth += 1;
+            }
+        }
+        Some(())
+    }
+
+    /// Turn a chain of entries, outermost first, into frames, innermost
+    /// first.
+    fn chain(&self, chain: &[(usize, Frame, Option<Location>)], lines: &Lines, address: Address) -> Vec<Frame> {
+        let mut location = lines.find(address);
+        let mut frames = Vec::new();
+        for (_, frame, call) in chain.iter().rev() {
+            let mut frame = frame.clone();
+            if let Some((file, line, column)) = location {
+                frame.file = lines.file(file);
+                frame.line = line;
+                frame.column = column;
+            }
+            frames.push(frame);
+            location = *call;
+        }
+        frames
+    }
+
+    /// The name of the function of an entry, preferring the demangled
+    /// linkage name of the entry or the entries it refers to.
+    fn function(&self, unit: &Unit, attributes: &[(u64, Value)], abbrevs: &HashMap<u64, Abbrev>, hops: u32) -> Option<String> {
+        let mut name = None;
+        for (attribute, value) in attributes {
+            match *attribute {
+                DW_AT_LINKAGE_NAME | DW_AT_MIPS_LINKAGE_NAME => match self.string(unit, value) {
+                    Some(linkage) if linkage.starts_with("_ZN") => return Some(demangle(&linkage).into_owned()),
+                    _ => (),
+                },
+                DW_AT_NAME => name = self.string(unit, value),
+                _ => (),
+            }
+        }
+        for (attribute, value) in attributes {
+            if let (DW_AT_ABSTRACT_ORIGIN | DW_AT_SPECIFICATION, &Value::Reference(offset), true) = (*attribute, value, hops > 0) {
+                let origin = match (unit.offset..unit.end).contains(&offset) {
+                    true => self.function_at(unit, abbrevs, offset, hops - 1),
+                    // References outside the unit use the other unit's
+                    // abbreviations
+                    false => self.unit_at(offset).and_then(|(unit, abbrevs)| self.function_at(&unit, &abbrevs, offset, hops - 1)),
+                };
+                if origin.is_some() {
+                    return origin;
+                }
+            }
+        }
+        name
+    }
+
+    fn function_at(&self, unit: &Unit, abbrevs: &HashMap<u64, Abbrev>, offset: usize, hops: u32) -> Option<String> {
+        let mut r = self.reader(&self.info, offset);
+        let (_, attributes) = self.entry(&mut r, unit, abbrevs)??;
+        self.function(unit, &attributes, abbrevs, hops)
+    }
+
+    /// The unit containing the entry at `offset`, with its bases set, and
+    /// its abbreviations.
+    fn unit_at(&self, offset: usize) -> Option<(Unit, HashMap<u64, Abbrev>)> {
+        let mut start = 0;
+        loop {
+            let mut unit = self.unit(start)?;
+            if (unit.offset..unit.end).contains(&offset) {
+                let abbrevs = self.abbrevs(unit.abbrev_offset)?;
+                let (_, attributes) = self.entry(&mut self.reader(&self.info, unit.entries), &unit, &abbrevs)??;
+                unit.set_bases(&attributes);
+                return Some((unit, abbrevs));
+            }
+            start = unit.end;
+        }
+    }
+
+    /// Read the line table at `offset`.
+    fn lines(&self, unit: &Unit, offset: usize, comp_dir: Option<&str>) -> Option<Lines> {
+        let mut r = self.reader(&self.line, offset);
+        let (length, offset_size) = r.length()?;
+        let end = r.pos.checked_add(length)?;
+        let version = r.u16()?;
+        let mut header = Unit { version, offset_size, address_size: unit.address_size, ..*unit };
+        if version >= 5 {
+            header.address_size = r.u8()?;
+            r.u8()?;
+        }
+        let header_length = r.uint(offset_size as usize)? as usize;
+        let program = r.pos.checked_add(header_length)?;
+        let min_inst_length = r.u8()? as u64;
+        if version >= 4 {
+            r.u8()?;
+        }
+        r.u8()?;
+        let line_base = r.u8()? as i8 as i64;
+        let line_range = r.u8()? as u64;
+        let opcode_base = r.u8()?;
+        let opcode_lengths = r.bytes(opcode_base.saturating_sub(1) as usize)?;
+
+        let mut lines = Lines::default();
+        let mut directories = Vec::new();
+        if version >= 5 {
+            for files in [false, true] {
+                let formats: Vec<(u64, u64)> = (0..r.u8()?).map(|_| Some((r.uleb()?, r.uleb()?))).collect::<Option<_>>()?;
+                for _ in 0..r.uleb()? {
+                    let (mut path, mut directory) = (None, 0);
+                    for &(content, form) in &formats {
+                        let value = self.value(&mut r, &header, form, 0)?;
+                        match (content, &value) {
+                            (1, value) => path = self.string(&header, value),
+                            (2, &Value::Constant(index)) => directory = index,
+                            _ => (),
+                        }
+                    }
+                    match files {
+                        false => directories.push(path),
+                        true => lines.files.push(path.map(|path| join(directories.get(directory as usize).cloned().flatten().as_deref(), &path, comp_dir))),
+                    }
+                }
+            }
+        } else {
+            lines.file_base = 1;
+            directories.push(comp_dir.map(String::from));
+            loop {
+                let directory = r.cstr()?;
+                if directory.is_empty() {
+                    break;
+                }
+                directories.push(Some(directory));
+            }
+            loop {
+                let path = r.cstr()?;
+                if path.is_empty() {
+                    break;
+                }
+                let directory = r.uleb()?;
+                r.uleb()?;
+                r.uleb()?;
+                lines.files.push(Some(join(directories.get(directory as usize).cloned().flatten().as_deref(), &path, comp_dir)));
+            }
+        }
+
+        r.pos = program;
+        let (mut address, mut file, mut line, mut column): (Address, u64, i64, u64) = ((0, 0), 1, 1, 0);
+        while r.pos < end {
+            let opcode = r.u8()?;
+            let mut row = false;
+            if opcode >= opcode_base {
+                let adjusted = (opcode - opcode_base) as u64;
+                address.1 = address.1.wrapping_add((adjusted / line_range.max(1)).wrapping_mul(min_inst_length));
+                line = line.wrapping_add(line_base + (adjusted % line_range.max(1)) as i64);
+                row = true;
+            } else {
+                match opcode {
+                    0 => {
+                        let length = r.uleb()? as usize;
+                        let next = r.pos.checked_add(length)?;
+                        match r.u8()? {
+                            1 => {
+                                lines.rows.push(Some((address, file, line as u32, column as u32)));
+                                lines.rows.push(None);
+                                address = (0, 0);
+                                file = 1;
+                                line = 1;
+                                column = 0;
+                            },
+                            2 => address = r.address(length.saturating_sub(1) as u8)?,
+                            _ => (),
+                        }
+                        r.pos = next;
+                    },
+                    1 => row = true,
+                    2 => address.1 = address.1.wrapping_add(r.uleb()?.wrapping_mul(min_inst_length)),
+                    3 => line = line.wrapping_add(r.sleb()?),
+                    4 => file = r.uleb()?,
+                    5 => column = r.uleb()?,
+                    8 => address.1 = address.1.wrapping_add(((255 - opcode_base) as u64 / line_range.max(1)).wrapping_mul(min_inst_length)),
+                    9 => address.1 = address.1.wrapping_add(r.u16()? as u64),
+                    _ => for _ in 0..opcode_lengths[opcode as usize - 1] {
+                        r.uleb()?;
+                    },
+                }
+            }
+            if row {
+                lines.rows.push(Some((address, file, line as u32, column as u32)));
+            }
+        }
+        Some(lines)
+    }
+}
+
+/// Join a line table file name to its directory, relative to the
+/// compilation directory when it's inside it.
+fn join(directory: Option<&str>, path: &str, comp_dir: Option<&str>) -> String {
+    let path = match directory {
+        Some(directory) if !directory.is_empty() && !path.starts_with('/') => format!("{}/{}", directory.trim_end_matches('/'), path),
+        _ => path.to_owned(),
+    };
+    match comp_dir.and_then(|dir| path.strip_prefix(dir.trim_end_matches('/'))?.strip_prefix('/')) {
+        Some(relative) => relative.to_owned(),
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(data: Vec<u8>) -> DebugSection<'static> {
+        DebugSection { data: data.into(), targets: HashMap::new() }
+    }
+
+    /// `app::main` at 0x1000, with `app::net::parse_header` inlined at
+    /// 0x1004 from src/main.rs:12:5.
+    fn dwarf() -> Dwarf<'static> {
+        let abbrev = vec![
+            // compile unit: name, comp_dir, stmt_list, low_pc, high_pc
+            1, 0x11, 1, 0x03, 0x08, 0x1b, 0x08, 0x10, 0x17, 0x11, 0x01, 0x12, 0x06, 0, 0,
+            // subprogram: linkage_name, low_pc, high_pc
+            2, 0x2e, 1, 0x6e, 0x08, 0x11, 0x01, 0x12, 0x06, 0, 0,
+            // inlined subroutine: abstract_origin, low_pc, high_pc, call_file, call_line, call_column
+            3, 0x1d, 0, 0x31, 0x13, 0x11, 0x01, 0x12, 0x06, 0x58, 0x0b, 0x59, 0x0b, 0x57, 0x0b, 0, 0,
+            // abstract subprogram: linkage_name
+            4, 0x2e, 0, 0x6e, 0x08, 0, 0,
+            0,
+        ];
+
+        let mut entries = vec![1];
+        entries.extend(b"src/main.rs\0/tmp/app\0");
+        entries.extend(0u32.to_le_bytes());
+        entries.extend(0x1000u64.to_le_bytes());
+        entries.extend(0x100u32.to_le_bytes());
+        let origin = 11 + entries.len() as u32;
+        entries.push(4);
+        entries.extend(b"_ZN3app3net12parse_header17h0123456789abcdefE\0");
+        entries.push(2);
+        entries.extend(b"_ZN3app4main17h80d95ca387d501e6E\0");
+        entries.extend(0x1000u64.to_le_bytes());
+        entries.extend(0x20u32.to_le_bytes());
+        entries.push(3);
+        entries.extend(origin.to_le_bytes());
+        entries.extend(0x1004u64.to_le_bytes());
+        entries.extend(8u32.to_le_bytes());
+        entries.extend([1, 12, 5]);
+        entries.extend([0, 0]);
+        let mut info = (7 + entries.len() as u32).to_le_bytes().to_vec();
+        info.extend([4, 0, 0, 0, 0, 0, 8]);
+        info.extend(entries);
+
+        let mut header = vec![1, 1, 1, (-5i8) as u8, 14, 13, 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+        header.extend(b"src\0\0main.rs\0\x01\0\0net.rs\0\x01\0\0\0");
+        let program = [
+            // set_address 0x1000, line 10, copy
+            &[0, 9, 2][..], &0x1000u64.to_le_bytes(), &[3, 9, 1],
+            // net.rs:88:13 at 0x1004
+            &[4, 2, 3, 0xce, 0, 5, 13, 2, 4, 1],
+            // main.rs:12:13 at 0x100c
+            &[2, 8, 4, 1, 3, 0xb4, 0x7f, 1],
+            // end at 0x1020
+            &[2, 0x14, 0, 1, 1],
+        ].concat();
+        let mut line = (6 + header.len() as u32 + program.len() as u32).to_le_bytes().to_vec();
+        line.extend(4u16.to_le_bytes());
+        line.extend((header.len() as u32).to_le_bytes());
+        line.extend(header);
+        line.extend(program);
+
+        Dwarf {
+            little_endian: true,
+            info: section(info),
+            abbrev: section(abbrev),
+            line: section(line),
+            ..Dwarf::default()
+        }
+    }
+
+    #[test]
+    fn inline_chain() {
+        let frames = dwarf().frames(&[(0, 0x1005), (0, 0x100c), (0, 0x2000)]);
+        assert_eq!(frames[0], [
+            Frame { function: Some("app::net::parse_header".into()), file: Some("src/net.rs".into()), line: 88, column: 13 },
+            Frame { function: Some("app::main".into()), file: Some("src/main.rs".into()), line: 12, column: 5 },
+        ]);
+        assert_eq!(describe(&frames[0]), "`app::net::parse_header` inlined into `app::main` at src/main.rs:12:5");
+        assert_eq!(frames[1], [Frame { function: Some("app::main".into()), file: Some("src/main.rs".into()), line: 12, column: 13 }]);
+        assert!(frames[2].is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Runtime fallback for unproven sites, for `reachability-ld`.
+//!
+//! Setting `REACHABILITY_FALLBACK=1` in the linker wrapper's environment turns
+//! a `static` build's link failure into a report. When the link fails on
+//! `unreachable_static!()` sites, the wrapper writes the diagnostics to
+//! `<output>.reachability` and links again with a generated object defining
+//! every unproven site as a jump to `reachability_unproven_site`, which
+//! panics with the site's location when reached. Sites that were eliminated
+//! are unaffected, and the build succeeds with its unproven sites behaving
+//! like `std::unreachable!()`.
+//!
+//! Each site passes its own link name to the definition, so one shared jump
+//! is enough to name the site that was reached. The object is generated for
+//! x86-64 and AArch64 ELF targets.
+
+use std::{fs, io};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::ld;
+
+/// The symbol unproven sites are linked to.
+pub const FALLBACK_SYMBOL: &str = "reachability_unproven_site";
+
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+
+/// Name, type, flags, data, link, info, alignment and entry size.
+type SectionHeader<'a> = (u32, u32, u64, &'a [u8], u32, u32, u64, u64);
+
+/// The ELF machine of the first object file in `args`, where the args are
+/// `cc` style.
+pub fn machine(args: &[OsString]) -> io::Result<u16> {
+    let args = ld::expand_args(args)?;
+    for arg in &args {
+        let path = Path::new(arg);
+        if path.extension().is_some_and(|e| e == "o") {
+            let header = fs::read(path)?;
+            if let (Some(b"\x7fELF"), Some(machine)) = (header.get(..4), header.get(18..20)) {
+                return Ok(match header[5] {
+                    2 => u16::from_be_bytes([machine[0], machine[1]]),
+                    _ => u16::from_le_bytes([machine[0], machine[1]]),
+                });
+            }
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no ELF object files to link"))
+}
+
+/// A relocatable ELF object for `machine` defining each of `symbols` as a
+/// jump to [`FALLBACK_SYMBOL`].
+pub fn object(machine: u16, symbols: &[String]) -> io::Result<Vec<u8>> {
+    // The jump and its relocation type, offset and addend
+    let (code, relocation): (&[u8], (u32, u64, i64)) = match machine {
+        // jmp rel32, R_X86_64_PLT32
+        EM_X86_64 => (&[0xe9, 0, 0, 0, 0], (4, 1, -4)),
+        // b imm26, R_AARCH64_JUMP26
+        EM_AARCH64 => (&[0, 0, 0, 0x14], (282, 0, 0)),
+        _ => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("unsupported ELF machine {}", machine))),
+    };
+
+    let mut strtab = vec![0];
+    let mut symtab = vec![0; 24];
+    let mut symbol = |name: &str, info: u8, section: u16, size: u64| {
+        symtab.extend((strtab.len() as u32).to_le_bytes());
+        symtab.extend([info, 0]);
+        symtab.extend(section.to_le_bytes());
+        symtab.extend(0u64.to_le_bytes());
+        symtab.extend(size.to_le_bytes());
+        strtab.extend(name.as_bytes());
+        strtab.push(0);
+    };
+    // STB_GLOBAL with STT_NOTYPE and STT_FUNC
+    symbol(FALLBACK_SYMBOL, 0x10, 0, 0);
+    for name in symbols {
+        symbol(name, 0x12, 1, code.len() as u64);
+    }
+
+    let mut rela = Vec::new();
+    rela.extend(relocation.1.to_le_bytes());
+    rela.extend((1u64 << 32 | relocation.0 as u64).to_le_bytes());
+    rela.extend(relocation.2.to_le_bytes());
+
+    let shstrtab = b"\0.text\0.rela.text\0.symtab\0.strtab\0.shstrtab\0.note.GNU-stack\0";
+    let sections: [SectionHeader; 6] = [
+        (1, 1, 6, code, 0, 0, 4, 0),
+        (7, 4, 0x40, &rela, 3, 1, 8, 24),
+        (18, 2, 0, &symtab, 4, 1, 8, 24),
+        (26, 3, 0, &strtab, 0, 0, 1, 0),
+        (34, 3, 0, shstrtab, 0, 0, 1, 0),
+        (44, 1, 0, &[], 0, 0, 1, 0),
+    ];
+
+    let mut elf = vec![0; 64];
+    let mut headers = vec![0; 64];
+    for &(name, kind, flags, data, link, info, align, entsize) in &sections {
+        elf.resize(elf.len().next_multiple_of(align as usize), 0);
+        headers.extend(name.to_le_bytes());
+        headers.extend(kind.to_le_bytes());
+        headers.extend(flags.to_le_bytes());
+        headers.extend(0u64.to_le_bytes());
+        headers.extend((elf.len() as u64).to_le_bytes());
+        headers.extend((data.len() as u64).to_le_bytes());
+        headers.extend(link.to_le_bytes());
+        headers.extend(info.to_le_bytes());
+        headers.extend(align.to_le_bytes());
+        headers.extend(entsize.to_le_bytes());
+        elf.extend(data);
+    }
+    elf.resize(elf.len().next_multiple_of(8), 0);
+    let shoff = elf.len() as u64;
+    elf.extend(headers);
+
+    // ELFCLASS64, ELFDATA2LSB, ET_REL
+    elf[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+    elf[16..18].copy_from_slice(&1u16.to_le_bytes());
+    elf[18..20].copy_from_slice(&machine.to_le_bytes());
+    elf[20..24].copy_from_slice(&1u32.to_le_bytes());
+    elf[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+    elf[0x34..0x36].copy_from_slice(&64u16.to_le_bytes());
+    elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+    elf[0x3c..0x3e].copy_from_slice(&(sections.len() as u16 + 1).to_le_bytes());
+    elf[0x3e..0x40].copy_from_slice(&5u16.to_le_bytes());
+    Ok(elf)
+}
+
+/// Where the report for a link producing `output` is written.
+pub fn report_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".reachability");
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object;
+
+    #[test]
+    fn stub_object() {
+        let symbols = ["___unreachable_static___@app@src/main.rs:3:5: a == \"b\"".to_owned()];
+        let elf = object(EM_X86_64, &symbols).unwrap();
+        // The stub's own definitions are found like any surviving site
+        let references = object::scan(&elf).unwrap();
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].symbol, symbols[0]);
+        assert!(object(3, &symbols).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+//! Source inventory of this crate's macros and methods for
+//! `cargo reachability list`.
+//!
+//! Sources are scanned token by token rather than parsed, skipping comments
+//! and literals, so uses are found whether or not the code builds with the
+//! current features or cfgs. Each use is either a static proof, which fails
+//! to link if it isn't proven, or an unchecked one, which is *undefined
+//! behaviour* in release builds if it is ever reached. std's
+//! `unwrap_unchecked`, `unwrap_err_unchecked` and `unreachable_unchecked()`
+//! count as unchecked too.
+//!
+//! Packages come from `cargo metadata`: the workspace members, or every
+//! package of the build with dependencies.
+
+use std::{env, fmt, fs, io};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::json;
+
+/// What a use relies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    /// Proven by the optimizer, or fails to link.
+    Static,
+    /// Assumed, and *undefined behaviour* in release builds if wrong.
+    Unchecked,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Static => "static",
+            Kind::Unchecked => "unchecked",
+        }
+    }
+}
+
+/// Macros, by name.
+const MACROS: [(&str, Kind); 12] = [
+    ("unreachable_static", Kind::Static),
+    ("assert_static", Kind::Static),
+    ("assert_eq_static", Kind::Static),
+    ("assert_ne_static", Kind::Static),
+    ("assert_matches_static", Kind::Static),
+    ("checked_ops", Kind::Static),
+    ("unwrap_static", Kind::Static),
+    ("expect_static", Kind::Static),
+    ("unwrap_err_static", Kind::Static),
+    ("expect_err_static", Kind::Static),
+    ("unreachable_unchecked", Kind::Unchecked),
+    ("unchecked_ops", Kind::Unchecked),
+];
+
+/// Methods and functions, called after a `.` or `::`.
+const CALLS: [(&str, Kind); 9] = [
+    ("unwrap_static", Kind::Static),
+    ("unwrap_err_static", Kind::Static),
+    ("unwrap_debug_checked", Kind::Unchecked),
+    ("expect_debug_checked", Kind::Unchecked),
+    ("unwrap_err_debug_checked", Kind::Unchecked),
+    ("expect_err_debug_checked", Kind::Unchecked),
+    ("unwrap_unchecked", Kind::Unchecked),
+    ("unwrap_err_unchecked", Kind::Unchecked),
+    ("unreachable_unchecked", Kind::Unchecked),
+];
+
+/// A use of a macro or method in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Use {
+    /// The macro or method name, with a trailing `!` for macros.
+    pub name: String,
+    pub kind: Kind,
+    pub file: PathBuf,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for Use {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.name)
+    }
+}
+
+/// Find the uses in the source of `file`.
+pub fn scan(file: &Path, source: &str) -> Vec<Use> {
+    let mut uses = Vec::new();
+    let mut tokens = Tokens { s: source, pos: 0, line: 1, column: 1 };
+    // The previous token, to tell calls from definitions
+    let mut previous = "";
+    while let Some((token, line, column)) = tokens.next() {
+        let is_ident = token.starts_with(|c: char| c == '_' || c.is_alphabetic());
+        let next = tokens.peek();
+        let found = match (is_ident, next) {
+            (true, Some("!")) if previous != "fn" => MACROS.iter()
+                .find(|(name, _)| *name == token)
+                .map(|&(name, kind)| (format!("{}!", name), kind)),
+            (true, Some("(")) if previous == "." || previous == "::" => CALLS.iter()
+                .find(|(name, _)| *name == token)
+                .map(|&(name, kind)| (name.to_owned(), kind)),
+            _ => None,
+        };
+        if let Some((name, kind)) = found {
+            uses.push(Use { name, kind, file: file.to_owned(), line, column });
+        }
+        previous = token;
+    }
+    uses
+}
+
+/// Rust tokens, approximately: identifiers, `::`, and single punctuation
+/// characters, without whitespace, comments or literals.
+struct Tokens<'a> {
+    s: &'a str,
+    pos: usize,
+    line: u32,
+    column: u32,
+}
+
+impl<'a> Tokens<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        let mut copy = Tokens { ..*self };
+        copy.next().map(|(token, _, _)| token)
+    }
+
+    fn next(&mut self) -> Option<(&'a str, u32, u32)> {
+        loop {
+            let rest = &self.s[self.pos..];
+            let c = rest.chars().next()?;
+            let (line, column, start) = (self.line, self.column, self.pos);
+            if c.is_whitespace() {
+                self.advance(c.len_utf8());
+            } else if rest.starts_with("//") {
+                self.advance(rest.find('\n').unwrap_or(rest.len()));
+            } else if rest.starts_with("/*") {
+                let mut depth = 0;
+                let mut len = 0;
+                while len < rest.len() {
+                    if rest[len..].starts_with("/*") {
+                        depth += 1;
+                        len += 2;
+                    } else if rest[len..].starts_with("*/") {
+                        depth -= 1;
+                        len += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        len += rest[len..].chars().next().map_or(1, char::len_utf8);
+                    }
+                }
+                self.advance(len);
+            } else if let Some(len) = string_literal(rest) {
+                self.advance(len);
+            } else if c == '\'' {
+                // A char literal, or a lifetime or label
+                let mut chars = rest.char_indices().skip(1);
+                let len = match (chars.next(), chars.next()) {
+                    (Some((_, '\\')), _) => rest.get(3..).and_then(|r| r.find('\'')).map_or(rest.len(), |i| i + 4),
+                    (Some(_), Some((i, '\''))) => i + 1,
+                    _ => 1,
+                };
+                self.advance(len);
+            } else if c == '_' || c.is_alphanumeric() {
+                let len = rest.find(|c: char| !(c == '_' || c.is_alphanumeric())).unwrap_or(rest.len());
+                self.advance(len);
+                return Some((&self.s[start..self.pos], line, column));
+            } else {
+                let len = if rest.starts_with("::") { 2 } else { c.len_utf8() };
+                self.advance(len);
+                return Some((&self.s[start..self.pos], line, column));
+            }
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        for c in self.s[self.pos..self.pos + len].chars() {
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                },
+                _ => self.column += 1,
+            }
+        }
+        self.pos += len;
+    }
+}
+
+/// The length of the string literal `s` starts with, if any: `"..."`,
+/// `b"..."`, `c"..."`, or their raw forms.
+fn string_literal(s: &str) -> Option<usize> {
+    let prefix = s.find(['"', '#']).filter(|&i| i <= 2)?;
+    let raw = match &s[..prefix] {
+        "" | "b" | "c" => false,
+        "r" | "br" | "cr" => true,
+        _ => return None,
+    };
+    let rest = &s[prefix..];
+    if raw {
+        let hashes = rest.find(|c| c != '#')?;
+        if !rest[hashes..].starts_with('"') {
+            return None;
+        }
+        let end = format!("\"{}", "#".repeat(hashes));
+        let close = rest[hashes + 1..].find(&end).map_or(rest.len(), |i| hashes + 1 + i + end.len());
+        return Some(prefix + close);
+    }
+    if !rest.starts_with('"') {
+        return None;
+    }
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            '"' if !escaped => return Some(prefix + i + 1),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    Some(s.len())
+}
+
+/// The default marker of comments justifying unchecked uses.
+pub const SAFETY_MARKER: &str = "SAFETY:";
+
+/// How far above a use its `unsafe` block is looked for.
+const UNSAFE_LINES: usize = 8;
+
+/// Whether the use at `line` of `source` has a comment containing `marker`
+/// next to it: on its line, on the lines between it and the start of its
+/// `unsafe` block, or in the comments and attributes directly above either.
+pub fn justified(source: &str, line: u32, marker: &str) -> bool {
+    let lines: Vec<&str> = source.lines().collect();
+    let site = (line as usize).saturating_sub(1).min(lines.len().saturating_sub(1));
+    let is_unsafe = |line: &str| line.split(|c: char| !(c == '_' || c.is_alphanumeric())).any(|word| word == "unsafe");
+    let start = (site.saturating_sub(UNSAFE_LINES)..=site).rev()
+        .find(|&i| lines.get(i).is_some_and(|line| is_unsafe(line)))
+        .unwrap_or(site);
+
+    let comment = |line: &str| ["//", "/*"].iter().filter_map(|c| line.find(c)).min().map(|i| line[i..].contains(marker));
+    for &top in &[start, site] {
+        if lines.get(top..=site).unwrap_or(&[]).iter().any(|line| comment(line) == Some(true)) {
+            return true;
+        }
+        for line in lines[..top].iter().rev().map(|line| line.trim_start()) {
+            if line.starts_with('*') || line.starts_with("#[") || comment(line).is_some() {
+                if line.contains(marker) {
+                    return true;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+    false
+}
+
+/// A package whose sources are scanned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// The directory holding its `Cargo.toml`.
+    pub root: PathBuf,
+}
+
+/// The workspace members, or every package with `deps`, from
+/// `cargo metadata`. `cargo_args` select the manifest and features.
+pub fn packages(cargo_args: &[OsString], deps: bool) -> io::Result<Vec<Package>> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--format-version", "1"]).args(cargo_args);
+    if !deps {
+        command.arg("--no-deps");
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+    }
+    let metadata = json::parse(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut packages = Vec::new();
+    for package in metadata.get("packages").map_or(&[][..], json::Value::as_array) {
+        let name = package.get("name").and_then(json::Value::as_str);
+        let manifest = package.get("manifest_path").and_then(json::Value::as_str).map(Path::new);
+        if let (Some(name), Some(root)) = (name, manifest.and_then(Path::parent)) {
+            packages.push(Package { name: name.to_owned(), root: root.to_owned() });
+        }
+    }
+    Ok(packages)
+}
+
+/// The uses in every `.rs` file of `package`, skipping `target` and nested
+/// packages.
+pub fn scan_package(package: &Package) -> io::Result<Vec<Use>> {
+    let mut uses = Vec::new();
+    let mut dirs = vec![package.root.clone()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                let skip = entry.file_name() == "target" || entry.file_name().to_string_lossy().starts_with('.')
+                    || path.join("Cargo.toml").exists();
+                if !skip {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                // Skip files that aren't UTF-8, which rustc rejects anyway
+                if let Ok(source) = fs::read_to_string(&path) {
+                    let relative = path.strip_prefix(&package.root).unwrap_or(&path);
+                    uses.extend(scan(relative, &source));
+                }
+            }
+        }
+    }
+    uses.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    Ok(uses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses() {
+        let source = r##"
+            use reachability::{unreachable_static, OptionExt};
+            macro_rules! unreachable_static { () => {} }
+            fn unwrap_static(x: Option<u8>) -> u8 {
+                // unreachable_static!() in a comment
+                /* nested /* unwrap_static!(x) */ .unwrap_debug_checked() */
+                let s = "unreachable_unchecked!()"; let r = r#"x.unwrap_static()"#;
+                let c = '"'; let q = '\''; let l: &'static str = "";
+                if x.is_none() { reachability::unreachable_static!("none") }
+                unsafe { x.unwrap_debug_checked() + core::hint::unreachable_unchecked() + unreachable_unchecked!() }
+            }
+        "##;
+        let uses: Vec<_> = scan(Path::new("src/lib.rs"), source).iter().map(Use::to_string).collect();
+        assert_eq!(uses, [
+            "src/lib.rs:9:48: unreachable_static!",
+            "src/lib.rs:10:28: unwrap_debug_checked",
+            "src/lib.rs:10:65: unreachable_unchecked",
+            "src/lib.rs:10:91: unreachable_unchecked!",
+        ]);
+    }
+
+    #[test]
+    fn justifications() {
+        let source = r#"
+            // SAFETY: checked above
+            let a = unsafe { x.unwrap_debug_checked() };
+            let b = unsafe {
+                // Safety: not quite
+                x.unwrap_debug_checked()
+            };
+            let c = unsafe { x.unwrap_debug_checked() }; // SAFETY: same line
+            /* SAFETY:
+             * a block comment
+             */
+            #[allow(deprecated)]
+            let d = unsafe {
+                y.unwrap_unchecked()
+            };
+        "#;
+        let results: Vec<_> = scan(Path::new("src/lib.rs"), source).iter()
+            .map(|u| (u.line, justified(source, u.line, SAFETY_MARKER)))
+            .collect();
+        assert_eq!(results, [(3, true), (6, false), (8, true), (14, true)]);
+        assert!(justified(source, 6, "Safety:"));
+    }
+}
@@ -0,0 +1,297 @@
+//! Just enough JSON for reading `cargo` output, and for writing reports.
+
+use std::fmt;
+
+/// A parsed JSON value. Objects keep their keys in document order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Look up `key` in an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Value] {
+        match self {
+            Value::Array(values) => values,
+            _ => &[],
+        }
+    }
+}
+
+/// Writes compact JSON, or indented with `{:#}`. Integral numbers are
+/// written without a fraction.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let indent = if f.alternate() { Some(0) } else { None };
+        write_value(f, self, indent)
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter, value: &Value, indent: Option<usize>) -> fmt::Result {
+    let inner = indent.map(|n| n + 2);
+    let newline = |f: &mut fmt::Formatter, indent: Option<usize>| match indent {
+        Some(n) => write!(f, "\n{:n$}", "", n = n),
+        None => Ok(()),
+    };
+    match value {
+        Value::Null => write!(f, "null"),
+        Value::Bool(b) => write!(f, "{}", b),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+        Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+        Value::Number(_) => write!(f, "null"),
+        Value::String(s) => write_string(f, s),
+        Value::Array(values) if values.is_empty() => write!(f, "[]"),
+        Value::Array(values) => {
+            write!(f, "[")?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                newline(f, inner)?;
+                write_value(f, value, inner)?;
+            }
+            newline(f, indent)?;
+            write!(f, "]")
+        },
+        Value::Object(entries) if entries.is_empty() => write!(f, "{{}}"),
+        Value::Object(entries) => {
+            write!(f, "{{")?;
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                newline(f, inner)?;
+                write_string(f, key)?;
+                write!(f, "{}", if indent.is_some() { ": " } else { ":" })?;
+                write_value(f, value, inner)?;
+            }
+            newline(f, indent)?;
+            write!(f, "}}")
+        },
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// A syntax error, with the byte offset it was found at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub offset: usize,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid JSON at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for Error { }
+
+/// Parse a single JSON document.
+pub fn parse(s: &str) -> Result<Value, Error> {
+    let mut parser = Parser { s: s.as_bytes(), pos: 0 };
+    let value = parser.value()?;
+    parser.whitespace();
+    match parser.pos == s.len() {
+        true => Ok(value),
+        false => Err(parser.error()),
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> Error {
+        Error { offset: self.pos }
+    }
+
+    fn whitespace(&mut self) {
+        while self.s.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.whitespace();
+        let matches = self.s[self.pos..].starts_with(token.as_bytes());
+        if matches {
+            self.pos += token.len();
+        }
+        matches
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.whitespace();
+        match self.s.get(self.pos) {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                if !self.eat("}") {
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        if !self.eat(":") {
+                            return Err(self.error());
+                        }
+                        entries.push((key, self.value()?));
+                        if self.eat("}") {
+                            break;
+                        } else if !self.eat(",") {
+                            return Err(self.error());
+                        }
+                    }
+                }
+                Ok(Value::Object(entries))
+            },
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if !self.eat("]") {
+                    loop {
+                        values.push(self.value()?);
+                        if self.eat("]") {
+                            break;
+                        } else if !self.eat(",") {
+                            return Err(self.error());
+                        }
+                    }
+                }
+                Ok(Value::Array(values))
+            },
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'-') | Some(b'0'..=b'9') => {
+                let start = self.pos;
+                while self.s.get(self.pos).is_some_and(|&b| b.is_ascii_digit() || b"+-.eE".contains(&b)) {
+                    self.pos += 1;
+                }
+                std::str::from_utf8(&self.s[start..self.pos]).ok()
+                    .and_then(|n| n.parse().ok())
+                    .map(Value::Number)
+                    .ok_or(Error { offset: start })
+            },
+            _ if self.eat("null") => Ok(Value::Null),
+            _ if self.eat("true") => Ok(Value::Bool(true)),
+            _ if self.eat("false") => Ok(Value::Bool(false)),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        if self.s.get(self.pos) != Some(&b'"') {
+            return Err(self.error());
+        }
+        self.pos += 1;
+
+        let mut bytes = Vec::new();
+        loop {
+            let b = *self.s.get(self.pos).ok_or_else(|| self.error())?;
+            self.pos += 1;
+            match b {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.s.get(self.pos).ok_or_else(|| self.error())?;
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut c = self.hex4()?;
+                            if (0xd800..0xdc00).contains(&c) && self.s[self.pos..].starts_with(b"\\u") {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                c = 0x10000 + ((c - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        },
+                        _ => return Err(self.error()),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                b => bytes.push(b),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error())
+    }
+
+    fn hex4(&mut self) -> Result<u32, Error> {
+        let hex = self.s.get(self.pos..self.pos + 4)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error())?;
+        self.pos += 4;
+        Ok(hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_message() {
+        let value = parse(r#"{"reason":"compiler-artifact","target":{"kind":["test"],"name":"opt1"},"fresh":false,"executable":null,"n":[-1.5e3],"s":"\"a\"\n\u00e9\ud83d\ude00"}"#).unwrap();
+        assert_eq!(value.get("reason").and_then(Value::as_str), Some("compiler-artifact"));
+        assert_eq!(value.get("target").and_then(|t| t.get("kind")).map(Value::as_array), Some(&[Value::String("test".into())][..]));
+        assert_eq!(value.get("fresh").and_then(Value::as_bool), Some(false));
+        assert_eq!(value.get("executable"), Some(&Value::Null));
+        assert_eq!(value.get("n").map(Value::as_array), Some(&[Value::Number(-1500.0)][..]));
+        assert_eq!(value.get("s").and_then(Value::as_str), Some("\"a\"\n\u{e9}\u{1f600}"));
+
+        assert_eq!(parse(&value.to_string()), Ok(value.clone()));
+        assert_eq!(parse(&format!("{:#}", value)), Ok(value));
+        assert_eq!(format!("{:#}", parse(r#"{"a":[1,{}],"b":[]}"#).unwrap()), "{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": []\n}");
+        assert_eq!(Value::Array(vec![Value::Number(3.0), Value::Number(0.5), Value::String("\u{1}".into())]).to_string(), r#"[3,0.5,"\u0001"]"#);
+
+        assert_eq!(parse("[1,]"), Err(Error { offset: 3 }));
+        assert_eq!(parse("{} x"), Err(Error { offset: 3 }));
+    }
+}
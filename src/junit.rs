@@ -0,0 +1,190 @@
+//! JUnit XML reports of matrix builds for `cargo reachability matrix --junit`.
+//!
+//! Each configuration is a test suite, and each target a test case that
+//! passes when it links, or when it fails to link if it is expected to fail.
+//! Every site that made a target fail to link gets a failing test case of
+//! its own too, named after its baseline key (see [`crate::baseline::key`])
+//! so it keeps its name across edits elsewhere in the file:
+//!
+//! ```text
+//! <testsuite name="opt-level=3 lto=fat" tests="3" failures="2" errors="0">
+//!   <testcase classname="opt-level=3 lto=fat" name="app">
+//!     <failure type="unproven-site" message="`app` failed to link on 1 site">src/net.rs:88:13: bad header</failure>
+//!   </testcase>
+//!   <testcase classname="opt-level=3 lto=fat" name="app: app src/net.rs: bad header">
+//!     <failure type="unproven-site" message="src/net.rs:88:13: bad header"/>
+//!   </testcase>
+//!   <testcase classname="opt-level=3 lto=fat" name="fail"/>
+//! </testsuite>
+//! ```
+
+use std::fmt::{self, Write};
+use crate::baseline;
+use crate::matrix::{Expectations, Outcome, Results};
+use crate::report::Rule;
+
+/// The JUnit XML report of matrix `results`.
+pub struct JUnit<'a> {
+    pub results: &'a [Results],
+    pub expectations: &'a Expectations,
+}
+
+/// A test case and its failure or error, if any.
+struct Case {
+    name: String,
+    problem: Option<Problem>,
+}
+
+struct Problem {
+    /// `failure` or `error`.
+    element: &'static str,
+    rule: Rule,
+    message: String,
+    details: String,
+}
+
+impl Case {
+    fn passed(name: &str) -> Self {
+        Case { name: name.into(), problem: None }
+    }
+
+    fn failed(name: String, element: &'static str, rule: Rule, message: String, details: String) -> Self {
+        Case { name, problem: Some(Problem { element, rule, message, details }) }
+    }
+
+    fn has(&self, element: &str) -> bool {
+        self.problem.as_ref().is_some_and(|p| p.element == element)
+    }
+}
+
+impl JUnit<'_> {
+    fn cases(&self, results: &Results) -> Vec<Case> {
+        let mut cases = Vec::new();
+        for (target, outcome) in &results.targets {
+            let expected = self.expectations.link(&results.profile, target);
+            match outcome {
+                Outcome::Linked if expected => cases.push(Case::passed(target)),
+                Outcome::Linked => cases.push(Case::failed(target.clone(), "failure", Rule::UnexpectedLink,
+                    format!("`{}` linked but was expected to fail", target), String::new())),
+                Outcome::LinkFailed(_) if !expected => cases.push(Case::passed(target)),
+                Outcome::LinkFailed(diagnostics) => {
+                    let sites: Vec<_> = diagnostics.iter().filter_map(|d| d.site()).collect();
+                    let message = match sites.len() {
+                        0 => format!("`{}` failed to link", target),
+                        1 => format!("`{}` failed to link on 1 site", target),
+                        n => format!("`{}` failed to link on {} sites", target, n),
+                    };
+                    let details = sites.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n");
+                    cases.push(Case::failed(target.clone(), "failure", Rule::UnprovenSite, message, details));
+                    for site in sites {
+                        let name = format!("{}: {}", target, baseline::key(&site));
+                        cases.push(Case::failed(name, "failure", Rule::UnprovenSite, site.to_string(), String::new()));
+                    }
+                },
+                Outcome::Failed(error) => cases.push(Case::failed(target.clone(), "error", Rule::BuildError,
+                    format!("`{}` failed to build", target), error.clone())),
+            }
+        }
+        cases
+    }
+}
+
+impl fmt::Display for JUnit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let suites: Vec<_> = self.results.iter().map(|results| (results.profile.to_string(), self.cases(results))).collect();
+        let count = |cases: &[&Case], element: &str| cases.iter().filter(|case| case.has(element)).count();
+        let all: Vec<_> = suites.iter().flat_map(|(_, cases)| cases).collect();
+
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(f, r#"<testsuites name="cargo-reachability matrix" tests="{}" failures="{}" errors="{}">"#,
+            all.len(), count(&all, "failure"), count(&all, "error"))?;
+        for (name, cases) in &suites {
+            let cases: Vec<_> = cases.iter().collect();
+            writeln!(f, r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}">"#,
+                Escape(name), cases.len(), count(&cases, "failure"), count(&cases, "error"))?;
+            for case in cases {
+                write!(f, r#"    <testcase classname="{}" name="{}""#, Escape(name), Escape(&case.name))?;
+                let problem = match &case.problem {
+                    Some(problem) => problem,
+                    None => {
+                        writeln!(f, "/>")?;
+                        continue;
+                    },
+                };
+                writeln!(f, ">")?;
+                write!(f, r#"      <{} type="{}" message="{}""#, problem.element, problem.rule.id(), Escape(&problem.message))?;
+                match &problem.details[..] {
+                    "" => writeln!(f, "/>")?,
+                    details => writeln!(f, ">{}</{}>", Escape(details), problem.element)?,
+                }
+                writeln!(f, "    </testcase>")?;
+            }
+            writeln!(f, "  </testsuite>")?;
+        }
+        writeln!(f, "</testsuites>")
+    }
+}
+
+/// Text escaped for XML attributes and content.
+struct Escape<'a>(&'a str);
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '&' => f.write_str("&amp;")?,
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '"' => f.write_str("&quot;")?,
+                '\'' => f.write_str("&apos;")?,
+                '\n' | '\t' => f.write_char(c)?,
+                // Not allowed in XML 1.0 at all
+                c if c.is_control() => (),
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ld::Diagnostic;
+    use crate::matrix::Profile;
+
+    #[test]
+    fn report() {
+        let profiles = Profile::matrix();
+        let site = Diagnostic { symbol: "___unreachable_static___@app::net@src/net.rs:88:13: bad <header>".into(), functions: Vec::new() };
+        let results = [
+            Results { profile: profiles[11], targets: vec![
+                ("app".into(), Outcome::LinkFailed(vec![site])),
+                ("fail".into(), Outcome::LinkFailed(Vec::new())),
+                ("opt1".into(), Outcome::Failed("error[E0425]: cannot find value `x`".into())),
+            ] },
+            Results { profile: profiles[18], targets: vec![("app".into(), Outcome::Linked), ("fail".into(), Outcome::Linked)] },
+        ];
+        let expectations = Expectations { fail: vec!["fail".into()], links: Vec::new() };
+        assert_eq!(JUnit { results: &results, expectations: &expectations }.to_string(), r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="cargo-reachability matrix" tests="6" failures="2" errors="1">
+  <testsuite name="opt-level=3 lto=fat" tests="4" failures="2" errors="1">
+    <testcase classname="opt-level=3 lto=fat" name="app">
+      <failure type="unproven-site" message="`app` failed to link on 1 site">src/net.rs:88:13: bad &lt;header&gt;</failure>
+    </testcase>
+    <testcase classname="opt-level=3 lto=fat" name="app: app src/net.rs: bad &lt;header&gt;">
+      <failure type="unproven-site" message="src/net.rs:88:13: bad &lt;header&gt;"/>
+    </testcase>
+    <testcase classname="opt-level=3 lto=fat" name="fail"/>
+    <testcase classname="opt-level=3 lto=fat" name="opt1">
+      <error type="build-error" message="`opt1` failed to build">error[E0425]: cannot find value `x`</error>
+    </testcase>
+  </testsuite>
+  <testsuite name="opt-level=0 lto=off debug-assertions" tests="2" failures="0" errors="0">
+    <testcase classname="opt-level=0 lto=off debug-assertions" name="app"/>
+    <testcase classname="opt-level=0 lto=off debug-assertions" name="fail"/>
+  </testsuite>
+</testsuites>
+"#);
+    }
+}
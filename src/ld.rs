@@ -0,0 +1,422 @@
+//! Linker output parsing for the `reachability-ld` linker wrapper.
+//!
+//! `reachability-ld` forwards its arguments to the real linker (`cc` unless
+//! `REACHABILITY_LINKER` says otherwise). When the link fails it reads the
+//! linker's errors for undefined `unreachable_static!()` symbols and prints a
+//! diagnostic pointing at each site that survived optimization:
+//!
+//! ```text
+//! error: `unreachable_static!()` was not eliminated: bad header
+//!   --> src/net.rs:88:13
+//!    |
+//! 88 |         None => reachability::unreachable_static!("bad header"),
+//!    |                 ^
+//!    |
+//!    = note: referenced from `app::net::parse_header`
+//! ```
+//!
+//! Use it with `-C linker=reachability-ld`, or `linker = "reachability-ld"` in
+//! `.cargo/config.toml`. rustc treats any linker named `*-ld` as a bare `ld`,
+//! so GNU ld style arguments are translated back into `cc` driver arguments
+//! before they're forwarded.
+
+use std::{fmt, fs, io};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use crate::demangle::demangle;
+use crate::symbol::{self, Site};
+
+/// An undefined symbol reported by the linker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndefinedReference {
+    /// The undefined symbol's link name.
+    pub symbol: String,
+    /// The demangled name of the function referencing the symbol, if known.
+    pub function: Option<String>,
+}
+
+/// Parse undefined symbol errors out of GNU ld, gold, lld, ld64 or
+/// `link.exe` output.
+pub fn undefined_references(output: &str) -> Vec<UndefinedReference> {
+    let mut references = Vec::new();
+    let mut function = None;
+    let mut lines = output.lines().map(str::trim_end).peekable();
+    while let Some(line) = lines.next() {
+        if let Some(symbol) = after(line, "error: undefined symbol: ") {
+            // lld: one `>>> referenced by` block per reference
+            let mut referenced = false;
+            while let Some(next) = lines.peek().and_then(|l| l.trim_start().strip_prefix(">>>")) {
+                lines.next();
+                let next = next.trim();
+                if let Some(location) = next.strip_prefix("referenced by ") {
+                    references.push(UndefinedReference {
+                        symbol: symbol.into(),
+                        function: object_function(location),
+                    });
+                    referenced = true;
+                } else if let (true, Some(function)) = (referenced, object_function(next)) {
+                    if let Some(reference) = references.last_mut() {
+                        reference.function = Some(function);
+                    }
+                }
+            }
+            if !referenced {
+                references.push(UndefinedReference { symbol: symbol.into(), function: None });
+            }
+        } else if let Some(name) = after(line, "in function ").and_then(unquote_start) {
+            // GNU ld: the context line preceding its undefined references
+            function = name.strip_suffix(':').and_then(unquote_end).map(|name| demangle(name).into_owned());
+        } else if let Some(i) = line.find("undefined reference to ") {
+            let symbol = &line[i + 23..];
+            let symbol = unquote_start(symbol).unwrap_or(symbol);
+            references.push(UndefinedReference {
+                symbol: unquote_end(symbol).unwrap_or(symbol).into(),
+                function: object_function(&line[..i]).or_else(|| function.clone()),
+            });
+        } else if line.contains("Undefined symbols for architecture") {
+            // ld64: `"symbol", referenced from:` followed by `function in object`
+            let mut symbol = None;
+            while let Some(next) = lines.peek().filter(|l| l.starts_with(' ')) {
+                let next = next.trim();
+                if let Some(name) = next.strip_prefix('"').and_then(|s| s.strip_suffix("\", referenced from:")) {
+                    symbol = Some(name.to_owned());
+                } else if let (Some(symbol), Some(i)) = (&symbol, next.rfind(" in ")) {
+                    references.push(UndefinedReference {
+                        symbol: symbol.clone(),
+                        function: Some(demangle(&next[..i]).into_owned()),
+                    });
+                }
+                lines.next();
+            }
+        } else if let Some(rest) = after(line, "unresolved external symbol ") {
+            // link.exe: `symbol referenced in function function`
+            let (symbol, function) = match rest.split_once(" referenced in function ") {
+                Some((symbol, function)) => (symbol, Some(demangle(function).into_owned())),
+                None => (rest, None),
+            };
+            references.push(UndefinedReference { symbol: symbol.into(), function });
+        }
+    }
+    references
+}
+
+/// Extract `function` from lld's and GNU ld's `object:(function)` locations.
+fn object_function(location: &str) -> Option<String> {
+    let start = location.rfind(":(")? + 2;
+    let function = location[start..].split(')').next()?;
+    let function = function.split('+').next()?.trim_start_matches(".text.");
+    Some(demangle(function).into_owned())
+}
+
+/// GNU ld quotes with `` `sym' ``, or `‘sym’` in UTF-8 locales.
+fn unquote_start(s: &str) -> Option<&str> {
+    s.strip_prefix('`').or_else(|| s.strip_prefix('\'')).or_else(|| s.strip_prefix('‘'))
+}
+
+fn unquote_end(s: &str) -> Option<&str> {
+    s.strip_suffix('\'').or_else(|| s.strip_suffix('’'))
+}
+
+fn after<'a>(line: &'a str, pattern: &str) -> Option<&'a str> {
+    line.find(pattern).map(|i| &line[i + pattern.len()..])
+}
+
+/// An `unreachable_static!()` site that failed to link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The site's link name.
+    pub symbol: String,
+    /// Functions referencing the site, demangled and deduplicated.
+    pub functions: Vec<String>,
+}
+
+impl Diagnostic {
+    /// The site's decoded source location.
+    pub fn site(&self) -> Option<Site<'_>> {
+        symbol::decode(&self.symbol)
+    }
+}
+
+/// Collect the `unreachable_static!()` sites among the linker's undefined
+/// symbols, in the order they were first reported.
+pub fn diagnostics(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for reference in undefined_references(output) {
+        if !symbol::is_marker(&reference.symbol) {
+            continue;
+        }
+        let index = match diagnostics.iter().position(|d| d.symbol == reference.symbol) {
+            Some(index) => index,
+            None => {
+                diagnostics.push(Diagnostic { symbol: reference.symbol, functions: Vec::new() });
+                diagnostics.len() - 1
+            },
+        };
+        let functions = &mut diagnostics[index].functions;
+        if let Some(function) = reference.function {
+            if !functions.contains(&function) {
+                functions.push(function);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The argument lifting lld's limit of 20 errors when linking with
+/// `linker`, if lld stopped reporting errors in `output`. GNU ld and ld64
+/// report every undefined symbol, so they need none.
+pub fn error_limit_arg(linker: &OsStr, output: &str) -> Option<&'static str> {
+    if !output.contains("too many errors emitted, stopping now") {
+        return None;
+    }
+    Some(if is_cc_driver(linker) { "-Wl,--error-limit=0" } else { "--error-limit=0" })
+}
+
+/// Renders rustc style, with a source snippet when the file can be read from
+/// the current directory.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let site = match self.site() {
+            Some(site) => site,
+            None => return write!(f, "error: `unreachable_static!()` was not eliminated: {}", self.symbol),
+        };
+
+        write!(f, "error: `unreachable_static!()` was not eliminated")?;
+        if let Some(message) = site.message {
+            write!(f, ": {}", message)?;
+        }
+        let gutter = " ".repeat(site.line.to_string().len());
+        write!(f, "\n{}--> {}:{}:{}\n{} |", gutter, site.file, site.line, site.column, gutter)?;
+
+        let source = fs::read_to_string(site.file).ok()
+            .and_then(|source| source.lines().nth((site.line as usize).saturating_sub(1)).map(String::from));
+        if let Some(source) = source {
+            let indent: String = source.chars().take((site.column as usize).saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(f, "\n{} | {}\n{} | {}^\n{} |", site.line, source, gutter, indent, gutter)?;
+        }
+
+        for function in &self.functions {
+            write!(f, "\n{} = note: referenced from `{}`", gutter, function)?;
+        }
+        if site.crate_name() != site.module_path || self.functions.is_empty() {
+            write!(f, "\n{} = note: in module `{}`", gutter, site.module_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `linker` is a compiler driver (`cc`, `gcc`, `clang`, ...) rather
+/// than a bare linker.
+pub fn is_cc_driver(linker: &OsStr) -> bool {
+    let name = Path::new(linker).file_stem().and_then(OsStr::to_str).unwrap_or("");
+    !(name == "ld" || name.ends_with("-ld") || name.starts_with("ld.") || name.contains("lld") || name == "link")
+}
+
+/// Translate the GNU ld style arguments rustc passes to a `*-ld` linker into
+/// arguments for a `cc` driver.
+///
+/// Arguments already meant for a driver (any `-Wl,` argument) are returned
+/// unchanged. Response files (`@file`) are rewritten into a new file next to
+/// the original.
+pub fn cc_args(args: Vec<OsString>) -> io::Result<Vec<OsString>> {
+    let response_file = args.iter().find_map(|a| a.to_str()?.strip_prefix('@')).map(String::from);
+    let expanded = expand_args(&args)?;
+
+    let is_ld = |a: &OsString| a.to_str().is_some_and(|a| a.starts_with("--") || a == "-z");
+    let is_cc = |a: &OsString| a.to_str().is_some_and(|a| a.starts_with("-Wl,") || a == "-Xlinker");
+    let args = if expanded.iter().any(is_ld) && !expanded.iter().any(is_cc) {
+        translate(expanded)
+    } else {
+        expanded
+    };
+
+    match response_file {
+        Some(path) => {
+            let path = format!("{}.reachability-ld", path);
+            let mut contents = String::new();
+            for arg in &args {
+                for c in arg.to_string_lossy().chars() {
+                    if c == '\\' || c.is_whitespace() {
+                        contents.push('\\');
+                    }
+                    contents.push(c);
+                }
+                contents.push('\n');
+            }
+            fs::write(&path, contents)?;
+            Ok(vec![format!("@{}", path).into()])
+        },
+        None => Ok(args),
+    }
+}
+
+/// Expand the first response file (`@file`) in `args`, as rustc only writes
+/// one.
+pub fn expand_args(args: &[OsString]) -> io::Result<Vec<OsString>> {
+    let mut expanded = Vec::new();
+    let mut response_file = false;
+    for arg in args {
+        match arg.to_str().and_then(|a| a.strip_prefix('@')) {
+            Some(path) if !response_file => {
+                expanded.extend(parse_response_file(&fs::read_to_string(path)?).into_iter().map(OsString::from));
+                response_file = true;
+            },
+            _ => expanded.push(arg.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+/// The output file of a link with `args`.
+pub fn output(args: &[OsString]) -> io::Result<Option<PathBuf>> {
+    let args = expand_args(args)?;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            return Ok(args.next().map(PathBuf::from));
+        }
+    }
+    Ok(None)
+}
+
+fn translate(args: Vec<OsString>) -> Vec<OsString> {
+    const DRIVER: &[&str] = &["-pie", "-no-pie", "-static", "-static-pie", "-shared", "-nostdlib", "-nostartfiles", "-nodefaultlibs"];
+    const SEPARATE: &[&str] = &["-z", "-m", "-e", "-T", "-soname", "-rpath", "-plugin", "--version-script", "--dynamic-list"];
+
+    let mut translated = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let s = arg.to_str().unwrap_or("");
+        if s == "-o" || s == "-L" || s == "-l" {
+            translated.push(arg);
+            translated.extend(args.next());
+        } else if !s.starts_with('-') || s.starts_with("-l") || s.starts_with("-L") || DRIVER.contains(&s) {
+            translated.push(arg);
+        } else {
+            let value = if SEPARATE.contains(&s) { args.next() } else { None };
+            for arg in Some(arg).into_iter().chain(value) {
+                translated.push("-Xlinker".into());
+                translated.push(arg);
+            }
+        }
+    }
+    translated
+}
+
+/// Split a GNU style response file, as written by rustc.
+fn parse_response_file(contents: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut chars = contents.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => arg.get_or_insert_with(String::new).extend(chars.next()),
+            c if c.is_whitespace() => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LLD: &str = "\
+rust-lld: error: undefined symbol: ___unreachable_static___@app::net@src/net.rs:88:13: bad header
+>>> referenced by net.rs:88 (src/net.rs:88)
+>>>               /tmp/app/target/release/deps/app-1234.app.o:(app::net::parse::h80d95ca387d501e6)
+>>> referenced by app-1234.app.o:(_ZN3app4main17h80d95ca387d501e6E)
+rust-lld: error: undefined symbol: ___unreachable_static___@app@src/main.rs:3:5
+>>> referenced by main.rs:3 (src/main.rs:3)
+>>>               /tmp/app/target/release/deps/app-1234.app.o:(app::main::h80d95ca387d501e6)
+rust-lld: error: undefined symbol: foo
+collect2: error: ld returned 1 exit status
+";
+
+    const BFD: &str = "\
+/usr/bin/ld: /tmp/app/target/release/deps/app-1234.app.o: in function `app::net::parse':
+/tmp/app/src/net.rs:88: undefined reference to `___unreachable_static___@app::net@src/net.rs:88:13: bad header'
+/usr/bin/ld: /tmp/app/src/net.rs:90: undefined reference to `___unreachable_static___@app::net@src/net.rs:88:13: bad header'
+/usr/bin/ld: app.o:(.text._ZN3app4main17h80d95ca387d501e6E+0x1d): undefined reference to `___unreachable_static___@app@src/main.rs:3:5'
+collect2: error: ld returned 1 exit status
+";
+
+    const LD64: &str = "\
+Undefined symbols for architecture arm64:
+  \"____unreachable_static___@app@src/main.rs:3:5\", referenced from:
+      __ZN3app4main17h80d95ca387d501e6E in app.o
+ld: symbol(s) not found for architecture arm64
+";
+
+    #[test]
+    fn lld() {
+        let diagnostics = diagnostics(LLD);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].functions, ["app::net::parse", "app::main"]);
+        assert_eq!(diagnostics[1].functions, ["app::main"]);
+        assert_eq!(diagnostics[0].site().unwrap().message, Some("bad header"));
+        assert_eq!(undefined_references(LLD).len(), 4);
+    }
+
+    #[test]
+    fn error_limit() {
+        let truncated = format!("{}rust-lld: error: too many errors emitted, stopping now (use --error-limit=0 to see all errors)\n", LLD);
+        assert_eq!(error_limit_arg("cc".as_ref(), &truncated), Some("-Wl,--error-limit=0"));
+        assert_eq!(error_limit_arg("rust-lld".as_ref(), &truncated), Some("--error-limit=0"));
+        assert_eq!(error_limit_arg("cc".as_ref(), LLD), None);
+        assert_eq!(error_limit_arg("cc".as_ref(), BFD), None);
+    }
+
+    #[test]
+    fn bfd() {
+        let diagnostics = diagnostics(BFD);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].functions, ["app::net::parse"]);
+        assert_eq!(diagnostics[1].site().unwrap().line, 3);
+    }
+
+    #[test]
+    fn bfd_utf8() {
+        let utf8 = BFD.replace('`', "‘").replace('\'', "’");
+        assert_eq!(diagnostics(&utf8), diagnostics(BFD));
+        assert_eq!(undefined_references("/usr/bin/ld: app.o: undefined reference to ‘")[0].symbol, "");
+    }
+
+    #[test]
+    fn ld64() {
+        let diagnostics = diagnostics(LD64);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].functions, ["app::main"]);
+        assert_eq!(diagnostics[0].site().unwrap().file, "src/main.rs");
+    }
+
+    #[test]
+    fn render() {
+        let diagnostic = &diagnostics(LLD)[0];
+        assert_eq!(diagnostic.to_string(), "\
+error: `unreachable_static!()` was not eliminated: bad header
+  --> src/net.rs:88:13
+   |
+   = note: referenced from `app::net::parse`
+   = note: referenced from `app::main`
+   = note: in module `app::net`");
+    }
+
+    #[test]
+    fn translate_ld_args() {
+        let args = ["a.o", "--as-needed", "-lc", "-L", "/lib", "-z", "now", "-pie", "-o", "out"];
+        let args = cc_args(args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args, ["a.o", "-Xlinker", "--as-needed", "-lc", "-L", "/lib", "-Xlinker", "-z", "-Xlinker", "now", "-pie", "-o", "out"]);
+
+        let args = ["a.o", "-Wl,--as-needed", "-o", "out"];
+        assert_eq!(cc_args(args.iter().map(OsString::from).collect()).unwrap(), args);
+
+        assert_eq!(parse_response_file("a\\ b\nc\\\\d\n"), ["a b", "c\\d"]);
+        assert!(is_cc_driver("cc".as_ref()) && is_cc_driver("/usr/bin/x86_64-linux-gnu-gcc".as_ref()));
+        assert!(!is_cc_driver("ld.lld".as_ref()) && !is_cc_driver("reachability-ld".as_ref()));
+    }
+}
@@ -1,4 +1,4 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 //! `unreachable_static!()` is a compile-time variant of the `std::unreachable!()`
 //! macro. The optimizer or linker must be able prove that the statement is
 //! unreachable otherwise the program will fail to compile.
@@ -21,10 +21,19 @@
 /// This macro behaves like `std::unreachable!()` unless explicitly opted into
 /// by a binary crate with the `static` feature, which should only be done when
 /// building release binaries (LTO is recommended but not required). Libraries
-/// depending on this crate **should not** enable this feature.
+/// depending on this crate **should not** enable this feature. They can
+/// select a mode for their own sites instead (see [Modes](#modes)), leaving
+/// the binary to choose which crates to enforce when linking through
+/// `reachability-ld` (see `reachability::scope`).
+///
+/// Builds with debug assertions keep behaving like `std::unreachable!()` even
+/// with `static`. Release profiles that keep debug assertions can enable the
+/// `always-static` feature instead, which enforces sites regardless.
 ///
 /// `unreachable_static!(!)` can be used to bypass this and always statically
-/// assert.
+/// assert, unless the crate selects another mode with the `reachability` cfg
+/// (see [Modes](#modes)), which `!` follows as well: with
+/// `reachability="panic"`, it panics at runtime.
 ///
 /// ## Consistency
 ///
@@ -33,30 +42,298 @@
 /// cannot be executed. Changes in compiler versions, optimization levels, or
 /// LTO settings may cause code that previously worked to fail with a linker
 /// error, so be careful in how you use this!
+///
+/// ## Link errors
+///
+/// Each expansion references its own undefined symbol named after its source
+/// location and message (see [`symbol`] for the naming scheme), so the
+/// linker's "undefined reference" error points at the site that survived:
+///
+/// ```text
+/// undefined symbol: ___unreachable_static___@app::net@src/net.rs:88:13: bad header
+/// ```
+///
+/// Messages become part of the symbol name and must be string literals.
+///
+/// When linking through `reachability-ld` with `REACHABILITY_FALLBACK=1`, a
+/// link that fails only on surviving sites is retried with those sites
+/// panicking when reached, and the diagnostics are written next to the
+/// output instead (see `reachability::fallback`).
+///
+/// When the linker doesn't name the sites, `cargo reachability bisect` finds
+/// them by rebuilding with only some sites enforced (see [`bisect`]), and
+/// `cargo reachability explain` lists the optimizations LLVM missed around
+/// them (see `reachability::remarks`).
+///
+/// Tests can check that a site is proven, or that it isn't, by building a
+/// separate crate under a given profile with `reachability::testkit` (with
+/// the `std` feature).
+///
+/// ## Warn mode
+///
+/// With the `warn` feature (which implies `static`), surviving sites don't
+/// fail the link. Each site registers itself in a link section that is
+/// discarded along with the site, and panics like `std::unreachable!()` if
+/// reached. `cargo reachability verify` then lists every site that survived
+/// in the linked binary (see [`registry`]), so a crate can be adopted
+/// gradually, fixing sites from one complete list per build.
+///
+/// ## Modes
+///
+/// The features apply to every crate in the build. A crate can instead select
+/// how its own sites behave with `--cfg reachability="<mode>"`, from
+/// `RUSTFLAGS` or a build script's `cargo:rustc-cfg` (`reachability::build`
+/// sets it from a `reachability.toml`), since the cfg is evaluated in the
+/// crate using the macros rather than in this one:
+///
+/// - `static`: fail to link on surviving sites, even with debug assertions.
+/// - `warn`: register surviving sites as described above.
+/// - `panic`: behave like `std::unreachable!()`.
+/// - `trap`: execute a trap instruction, without a message or location.
+/// - `unchecked`: behave like `std::hint::unreachable_unchecked()`. Reaching
+///   a site is *undefined behaviour*, so only select this for code whose
+///   sites are proven in another build.
+///
+/// The mode applies to `unreachable_static!(!)` and the other macros of this
+/// crate too. Without the cfg, the `static` and `warn` features decide as
+/// above. The macros allow `unexpected_cfgs` where they check the cfg, so
+/// crates using them needn't declare it.
 #[macro_export]
 macro_rules! unreachable_static {
     (!) => {
-        $crate::unreachable_static! { !:"" }
+        $crate::internal_unreachable_static_site!($crate::internal_unreachable_static_symbol!())
     };
     (!: $msg:expr) => {
+        $crate::internal_unreachable_static_site!($crate::internal_unreachable_static_symbol!($msg))
+    };
+    ($($tt:tt)*) => {
+        $crate::internal_unreachable_static! { $($tt)* }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unreachable_static_symbol {
+    () => {
+        $crate::_core::concat!(
+            "___unreachable_static___@", $crate::_core::module_path!(),
+            "@", $crate::_core::file!(),
+            ":", $crate::_core::line!(),
+            ":", $crate::_core::column!()
+        )
+    };
+    ($msg:expr) => {
+        $crate::_core::concat!(
+            $crate::internal_unreachable_static_symbol!(), ": ", $msg
+        )
+    };
+}
+
+/// Picks the branch for the `reachability` cfg of the calling crate, or
+/// `default` when it isn't set.
+///
+/// The calling crate may not declare the cfg, and lint attributes only apply
+/// to the cfgs of the nodes below them, so a module that allows
+/// `unexpected_cfgs` imports one of the `internal_pick_*` macros as `pick`.
+/// The branches are passed to it outside the module, keeping the lints of
+/// the caller's tokens in them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_reachability_mode {
+    (
+        static => { $($static:tt)* }
+        warn => { $($warn:tt)* }
+        panic => { $($panic:tt)* }
+        trap => { $($trap:tt)* }
+        unchecked => { $($unchecked:tt)* }
+        default => { $($default:tt)* }
+    ) => {
         {
-            extern {
-                // TODO include a message here in some way?
-                #[link_name = "___unreachable_static___"]
-                fn unreachable_static() -> !;
+            #[allow(unexpected_cfgs)]
+            mod __reachability_mode {
+                #[cfg(reachability = "static")]
+                pub(super) use $crate::internal_pick_1 as pick;
+                #[cfg(reachability = "warn")]
+                pub(super) use $crate::internal_pick_2 as pick;
+                #[cfg(reachability = "panic")]
+                pub(super) use $crate::internal_pick_3 as pick;
+                #[cfg(reachability = "trap")]
+                pub(super) use $crate::internal_pick_4 as pick;
+                #[cfg(reachability = "unchecked")]
+                pub(super) use $crate::internal_pick_5 as pick;
+                #[cfg(not(any(
+                    reachability = "static",
+                    reachability = "warn",
+                    reachability = "panic",
+                    reachability = "trap",
+                    reachability = "unchecked",
+                )))]
+                pub(super) use $crate::internal_pick_6 as pick;
             }
-            unsafe { unreachable_static(); }
+            __reachability_mode::pick!({ $($static)* } { $($warn)* } { $($panic)* } { $($trap)* } { $($unchecked)* } { $($default)* })
         }
     };
-    ($($tt:tt)*) => {
-        $crate::internal_unreachable_static! { $($tt)* }
+}
+
+/// Picks the branch for the `reachability_unchecked` cfg of the calling
+/// crate and its debug assertions, like [`internal_reachability_mode!`].
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unchecked_mode {
+    (
+        checked => { $($checked:tt)* }
+        unchecked => { $($unchecked:tt)* }
+    ) => {
+        {
+            #[allow(unexpected_cfgs)]
+            mod __reachability_unchecked_mode {
+                #[cfg(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked"))))]
+                pub(super) use $crate::internal_pick_1 as pick;
+                #[cfg(not(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked")))))]
+                pub(super) use $crate::internal_pick_2 as pick;
+            }
+            __reachability_unchecked_mode::pick!({ $($checked)* } { $($unchecked)* })
+        }
+    };
+}
+
+/// Expands to the first of the braced branches passed to it.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_pick_1 {
+    ({ $($a:tt)* } $($rest:tt)*) => { { $($a)* } };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_pick_2 {
+    ({ $($_:tt)* } $($rest:tt)*) => { $crate::internal_pick_1!($($rest)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_pick_3 {
+    ({ $($_:tt)* } $($rest:tt)*) => { $crate::internal_pick_2!($($rest)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_pick_4 {
+    ({ $($_:tt)* } $($rest:tt)*) => { $crate::internal_pick_3!($($rest)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_pick_5 {
+    ({ $($_:tt)* } $($rest:tt)*) => { $crate::internal_pick_4!($($rest)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_pick_6 {
+    ({ $($_:tt)* } $($rest:tt)*) => { $crate::internal_pick_5!($($rest)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unreachable_static_site {
+    ($symbol:expr) => {
+        $crate::internal_reachability_mode! {
+            static => { $crate::internal_unreachable_static_link!($symbol) }
+            warn => { $crate::internal_unreachable_static_register!($symbol) }
+            panic => { $crate::internal_unreachable_static_panic($symbol) }
+            trap => { $crate::internal_unreachable_static_trap() }
+            unchecked => { unsafe { $crate::_core::hint::unreachable_unchecked() } }
+            default => { $crate::internal_unreachable_static_default_site!($symbol) }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unreachable_static_link {
+    ($symbol:expr) => {
+        {
+            // Sites left out of a `cargo reachability bisect` build fall back
+            const ENFORCED: bool = $crate::bisect::enforced($crate::_core::option_env!("REACHABILITY_BISECT"), $symbol);
+            if ENFORCED {
+                extern "C-unwind" {
+                    #[link_name = $symbol]
+                    fn unreachable_static(symbol: *const u8, len: usize) -> !;
+                }
+                unsafe { unreachable_static($symbol.as_ptr(), $symbol.len()); }
+            } else {
+                $crate::internal_unreachable_static_panic($symbol)
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unreachable_static_register {
+    ($symbol:expr) => {
+        {
+            const SYMBOL: &str = $crate::_core::concat!($symbol, "\0");
+            #[cfg_attr(any(target_os = "macos", target_os = "ios"), link_section = "__DATA,__reach_sites")]
+            #[cfg_attr(not(any(target_os = "macos", target_os = "ios")), link_section = "reachability_sites")]
+            static SITE: [u8; SYMBOL.len()] = $crate::registry::descriptor(SYMBOL);
+            $crate::registry::reached(&SITE)
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "warn"))]
+macro_rules! internal_unreachable_static_default_site {
+    ($symbol:expr) => {
+        $crate::internal_unreachable_static_link!($symbol)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "warn")]
+macro_rules! internal_unreachable_static_default_site {
+    ($symbol:expr) => {
+        $crate::internal_unreachable_static_register!($symbol)
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-#[cfg(any(not(feature = "static"), debug_assertions))]
 macro_rules! internal_unreachable_static {
+    ($($tt:tt)*) => {
+        $crate::internal_reachability_mode! {
+            static => { $crate::internal_unreachable_static_enforced! { $($tt)* } }
+            warn => { $crate::internal_unreachable_static_enforced! { $($tt)* } }
+            panic => { $crate::_core::unreachable! { $($tt)* } }
+            trap => { $crate::internal_unreachable_static_enforced! { $($tt)* } }
+            unchecked => { $crate::internal_unreachable_static_enforced! { $($tt)* } }
+            default => { $crate::internal_unreachable_static_default! { $($tt)* } }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unreachable_static_enforced {
+    () => {
+        $crate::unreachable_static! { ! }
+    };
+    ($msg:literal $(, $($args:tt)*)?) => {
+        $crate::unreachable_static! { !: $msg }
+    };
+    ($($tt:tt)*) => {
+        $crate::unreachable_static! { ! }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+macro_rules! internal_unreachable_static_default {
     ($($tt:tt)*) => {
         $crate::_core::unreachable! { $($tt)* }
     };
@@ -64,10 +341,58 @@ macro_rules! internal_unreachable_static {
 
 #[doc(hidden)]
 #[macro_export]
-#[cfg(all(feature = "static", not(debug_assertions)))]
-macro_rules! internal_unreachable_static {
+#[cfg(all(feature = "static", any(not(debug_assertions), feature = "always-static")))]
+macro_rules! internal_unreachable_static_default {
     ($($tt:tt)*) => {
-        $crate::unreachable_static! { ! };
+        $crate::internal_unreachable_static_enforced! { $($tt)* }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_static_or {
+    ({ $($static:tt)* } else { $($fallback:tt)* }) => {
+        $crate::internal_reachability_mode! {
+            static => { $($static)* }
+            warn => { $($static)* }
+            panic => { $($fallback)* }
+            trap => { $($static)* }
+            unchecked => { $($static)* }
+            default => { $crate::internal_static_or_default! { { $($static)* } else { $($fallback)* } } }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+macro_rules! internal_static_or_default {
+    ({ $($static:tt)* } else { $($fallback:tt)* }) => {
+        $($fallback)*
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(all(feature = "static", any(not(debug_assertions), feature = "always-static")))]
+macro_rules! internal_static_or_default {
+    ({ $($static:tt)* } else { $($fallback:tt)* }) => {
+        $($static)*
+    };
+}
+
+/// `unreachable_static!()` with a message built by `concat!()`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_unreachable_static_expr {
+    ($msg:expr) => {
+        $crate::internal_static_or! {
+            {
+                $crate::unreachable_static!(!: $msg)
+            } else {
+                $crate::_core::panic!("{}", $msg)
+            }
+        }
     };
 }
 
@@ -79,60 +404,779 @@ macro_rules! internal_unreachable_static {
 /// `std::hint::unreachable_unchecked` in release builds. Reaching this panic
 /// means your code has *undefined behaviour* in release builds and must be
 /// fixed.
+///
+/// `--cfg reachability_unchecked="checked"` keeps the panic in builds without
+/// debug assertions, and `--cfg reachability_unchecked="unchecked"` drops it
+/// from builds with them. Like `reachability` (see [`unreachable_static!`]),
+/// the cfg is evaluated in the crate using the macro, and applies to
+/// [`unchecked_ops!`] and the `*_debug_checked` methods too, though the
+/// methods only see it when it is also set for this crate, e.g. through
+/// `RUSTFLAGS`.
 #[macro_export]
 macro_rules! unreachable_unchecked {
     (!) => {
         $crate::_core::hint::unreachable_unchecked()
     };
     ($($tt:tt)*) => {
-        {
-            #[cfg(debug_assertions)]
+        $crate::internal_unchecked_mode! {
+            checked => {
+                $crate::internal_unreachable_unchecked();
+                $crate::_core::unreachable!($($tt)*)
+            }
+            unchecked => { $crate::unreachable_unchecked!(!) }
+        }
+    };
+}
+
+/// Keeps `unreachable_unchecked!()` unsafe to call in debug builds.
+#[doc(hidden)]
+#[inline(always)]
+pub unsafe fn internal_unreachable_unchecked() { }
+
+/// Panics for a site with link name `symbol` in the `panic` mode.
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn internal_unreachable_static_panic(symbol: &str) -> ! {
+    match symbol::decode(symbol).and_then(|site| site.message) {
+        Some(message) => panic!("internal error: entered unreachable code: {}", message),
+        None => panic!("internal error: entered unreachable code"),
+    }
+}
+
+/// Traps for a site in the `trap` mode.
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub fn internal_unreachable_static_trap() -> ! {
+    unsafe { core::arch::asm!("ud2", options(noreturn)) }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
+pub fn internal_unreachable_static_trap() -> ! {
+    unsafe { core::arch::asm!("udf #0", options(noreturn)) }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+pub fn internal_unreachable_static_trap() -> ! {
+    unsafe { core::arch::asm!("unimp", options(noreturn)) }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(target_arch = "wasm32")]
+pub fn internal_unreachable_static_trap() -> ! {
+    core::arch::wasm32::unreachable()
+}
+
+/// Targets without a known trap instruction panic instead.
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(not(any(
+    target_arch = "x86", target_arch = "x86_64",
+    target_arch = "arm", target_arch = "aarch64",
+    target_arch = "riscv32", target_arch = "riscv64",
+    target_arch = "wasm32",
+)))]
+pub fn internal_unreachable_static_trap() -> ! {
+    panic!("internal error: entered unreachable code")
+}
+
+/// The definition `reachability-ld` links unproven sites to when
+/// `REACHABILITY_FALLBACK` is set (see `reachability::fallback`).
+///
+/// # Safety
+///
+/// `symbol` and `len` must be a site's link name.
+#[doc(hidden)]
+#[no_mangle]
+#[cold]
+pub unsafe extern "C-unwind" fn reachability_unproven_site(symbol: *const u8, len: usize) -> ! {
+    let symbol = core::str::from_utf8(core::slice::from_raw_parts(symbol, len)).unwrap_or("");
+    match symbol::decode(symbol) {
+        Some(site) => panic!("unproven `unreachable_static!()` reached at {}", site),
+        None => panic!("unproven `unreachable_static!()` reached"),
+    }
+}
+
+/// Compile-time variant of `std::assert!()`
+///
+/// Fail to compile if the compiler can't prove that the condition always
+/// holds. Follows the same opt-in as [`unreachable_static!`], behaving like
+/// `std::assert!()` unless the `static` feature is enabled in a release build.
+///
+/// ```no_run
+/// # // Can't doctest this due to https://github.com/rust-lang/cargo/issues/4251
+/// let v = [1, 2, 3];
+/// reachability::assert_static!(v.len() == 3);
+/// reachability::assert_static!(!v.is_empty(), "checked above");
+/// ```
+///
+/// Messages become part of the link name of the assertion when enforced, so
+/// only the leading string literal is kept. Without a message the stringified
+/// condition is used instead.
+#[macro_export]
+macro_rules! assert_static {
+    ($cond:expr $(,)?) => {
+        $crate::internal_static_or! {
             {
-                #[inline(always)]
-                unsafe fn unreachable_() -> ! {
-                    $crate::_core::unreachable!($($tt)*)
+                if !$cond {
+                    $crate::unreachable_static!(!: $crate::_core::concat!("assertion failed: ", $crate::_core::stringify!($cond)))
                 }
-                unreachable_()
+            } else {
+                $crate::_core::assert!($cond)
+            }
+        }
+    };
+    ($cond:expr, $msg:literal $(, $($args:tt)*)?) => {
+        $crate::internal_static_or! {
+            {
+                if !$cond {
+                    $crate::unreachable_static!(!: $msg)
+                }
+            } else {
+                $crate::_core::assert!($cond, $msg $(, $($args)*)?)
+            }
+        }
+    };
+    ($cond:expr, $($args:tt)+) => {
+        $crate::internal_static_or! {
+            {
+                $crate::assert_static!($cond)
+            } else {
+                $crate::_core::assert!($cond, $($args)+)
             }
-            #[cfg(not(debug_assertions))]
-            $crate::unreachable_unchecked!(!)
         }
     };
 }
 
-/* TODO make these optional features and use a proc macro?
+/// Compile-time variant of `std::assert_eq!()`
+///
+/// See [`assert_static!`].
+///
+/// ```no_run
+/// # // Can't doctest this due to https://github.com/rust-lang/cargo/issues/4251
+/// let (a, b) = (2, 3);
+/// reachability::assert_eq_static!(a + b, 5);
+/// ```
+#[macro_export]
+macro_rules! assert_eq_static {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::internal_assert_cmp_static!(==, $left, $right, $crate::_core::concat!(
+            "assertion `left == right` failed: ",
+            $crate::_core::stringify!($left), " == ", $crate::_core::stringify!($right),
+        ))
+    };
+    ($left:expr, $right:expr, $msg:literal $(, $($args:tt)*)?) => {
+        $crate::internal_assert_cmp_static!(==, $left, $right, $msg, $msg $(, $($args)*)?)
+    };
+}
+
+/// Compile-time variant of `std::assert_ne!()`
+///
+/// See [`assert_static!`].
+///
+/// ```no_run
+/// # // Can't doctest this due to https://github.com/rust-lang/cargo/issues/4251
+/// let (a, b) = (2, 3);
+/// reachability::assert_ne_static!(a, b);
+/// ```
+#[macro_export]
+macro_rules! assert_ne_static {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::internal_assert_cmp_static!(!=, $left, $right, $crate::_core::concat!(
+            "assertion `left != right` failed: ",
+            $crate::_core::stringify!($left), " != ", $crate::_core::stringify!($right),
+        ))
+    };
+    ($left:expr, $right:expr, $msg:literal $(, $($args:tt)*)?) => {
+        $crate::internal_assert_cmp_static!(!=, $left, $right, $msg, $msg $(, $($args)*)?)
+    };
+}
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_assert_cmp_static {
+    (==, $left:expr, $right:expr, $symbol:expr $(, $($args:tt)*)?) => {
+        $crate::internal_static_or! {
+            {
+                match (&$left, &$right) {
+                    (left, right) => if !(*left == *right) {
+                        $crate::unreachable_static!(!: $symbol)
+                    },
+                }
+            } else {
+                $crate::_core::assert_eq!($left, $right $(, $($args)*)?)
+            }
+        }
+    };
+    (!=, $left:expr, $right:expr, $symbol:expr $(, $($args:tt)*)?) => {
+        $crate::internal_static_or! {
+            {
+                match (&$left, &$right) {
+                    (left, right) => if *left == *right {
+                        $crate::unreachable_static!(!: $symbol)
+                    },
+                }
+            } else {
+                $crate::_core::assert_ne!($left, $right $(, $($args)*)?)
+            }
+        }
+    };
+}
+
+/// Compile-time assertion that an expression matches a pattern
+///
+/// See [`assert_static!`]. Falls back to a panic showing the `Debug` value of
+/// the expression, like the unstable `std::assert_matches!()`.
+///
+/// ```no_run
+/// # // Can't doctest this due to https://github.com/rust-lang/cargo/issues/4251
+/// let v: Result<u8, ()> = Ok(1);
+/// reachability::assert_matches_static!(v, Ok(1..=9));
+/// reachability::assert_matches_static!(v, Ok(x) if x > 0, "positive");
+/// ```
+#[macro_export]
+macro_rules! assert_matches_static {
+    ($expr:expr, $($pat:pat)|+ $(if $guard:expr)? $(,)?) => {
+        $crate::internal_assert_matches_static!($crate::_core::concat!(
+            "assertion failed: ", $crate::_core::stringify!($expr),
+            " matches ", $crate::_core::stringify!($($pat)|+ $(if $guard)?),
+        ), []; $expr, $($pat)|+ $(if $guard)?)
+    };
+    ($expr:expr, $($pat:pat)|+ $(if $guard:expr)?, $msg:literal $(, $($args:tt)*)?) => {
+        $crate::internal_assert_matches_static!($msg, [$msg $(, $($args)*)?]; $expr, $($pat)|+ $(if $guard)?)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_assert_matches_static {
+    ($symbol:expr, [$($args:tt)*]; $expr:expr, $($pat:pat)|+ $(if $guard:expr)?) => {
+        $crate::internal_static_or! {
+            {
+                match $expr {
+                    $($pat)|+ $(if $guard)? => (),
+                    _ => $crate::unreachable_static!(!: $symbol),
+                }
+            } else {
+                match $expr {
+                    $($pat)|+ $(if $guard)? => (),
+                    ref left => $crate::internal_assert_matches_failed!(
+                        left, $crate::_core::stringify!($($pat)|+ $(if $guard)?), [$($args)*]
+                    ),
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_assert_matches_failed {
+    ($left:expr, $right:expr, []) => {
+        $crate::_core::panic!("assertion `left matches right` failed\n  left: {:?}\n right: {}", $left, $right)
+    };
+    ($left:expr, $right:expr, [$($args:tt)+]) => {
+        $crate::_core::panic!("assertion `left matches right` failed: {}\n  left: {:?}\n right: {}",
+            $crate::_core::format_args!($($args)+), $left, $right)
+    };
+}
+
+/// Arithmetic that must be proven not to overflow.
+///
+/// Rewrites every arithmetic operator (`+ - * / % << >>` and unary `-`) in the
+/// expression into its `checked_*` form, and fails to compile like
+/// [`unreachable_static!`] if the compiler can't prove none of them
+/// overflows, divides by zero, or shifts out of range. Follows the same opt-in,
+/// panicking on overflow unless the `static` feature is enabled in a release
+/// build.
+///
+/// ```no_run
+/// # // Can't doctest this due to https://github.com/rust-lang/cargo/issues/4251
+/// fn digit(acc: u32, c: u8) -> u32 {
+///     let c = (c - b'0') as u32;
+///     if acc < 1000 && c < 10 {
+///         reachability::checked_ops!(acc * 10 + c)
+///     } else {
+///         0
+///     }
+/// }
+/// ```
+///
+/// Operands are anything between two operators: paths, literals, method
+/// calls, indexing and `as` casts. Parenthesized subexpressions are rewritten
+/// too, but arguments of function and method calls aren't. Shift amounts must
+/// be `u32`, unary `-` only applies to signed integers, and bitwise operators
+/// are supported for precedence but never fail. Comparison, logical,
+/// assignment and range operators aren't arithmetic and don't compile:
+///
+/// ```compile_fail
+/// let x = 1u32;
+/// let small = reachability::checked_ops!(x + 1 < 10);
+/// ```
+///
+/// Each operand takes a level or two of macro recursion, so expressions of
+/// more than about a hundred operands need a higher `recursion_limit`.
 #[macro_export]
 macro_rules! checked_ops {
-    // TODO this
-    ($expr:expr) => {
-        $expr
+    ($($tt:tt)+) => {
+        match $crate::internal_ops!(@munch Checked; [] [] $($tt)+).0 {
+            $crate::_core::option::Option::Some(v) => v,
+            $crate::_core::option::Option::None => $crate::internal_unreachable_static_expr!($crate::_core::concat!(
+                "arithmetic overflow: ", $crate::_core::stringify!($($tt)+)
+            )),
+        }
     };
 }
 
+/// Unsafe arithmetic that panics on overflow in debug builds.
+///
+/// Rewrites every arithmetic operator (`+ - * / % << >>` and unary `-`) in the
+/// expression into its `unchecked_*` form in release builds, where overflow,
+/// division by zero, or shifting out of range is *undefined behaviour*. Obeys
+/// the `debug-assertions` configuration option like [`unreachable_unchecked!`],
+/// panicking on any of them in debug builds.
+///
+/// ```
+/// fn sum(v: &[u32]) -> u32 {
+///     let mut sum = 0;
+///     for &x in v {
+///         // Safety: `v` never holds enough to overflow
+///         sum = unsafe { reachability::unchecked_ops!(sum + x) };
+///     }
+///     sum
+/// }
+/// # assert_eq!(sum(&[1, 2, 3]), 6);
+/// ```
+///
+/// Operands are handled the same as in [`checked_ops!`], so negating an
+/// unsigned integer doesn't compile in either mode:
+///
+/// ```compile_fail
+/// let x = 1u32;
+/// // Safety: none, `0 - x` overflows
+/// let y = unsafe { reachability::unchecked_ops!(-x) };
+/// ```
 #[macro_export]
 macro_rules! unchecked_ops {
-    // TODO this
-    ($expr:expr) => {
-        $expr
+    ($($tt:tt)+) => {
+        $crate::internal_unchecked_mode! {
+            checked => {
+                match $crate::internal_ops!(@munch Checked; [] [] $($tt)+).0 {
+                    $crate::_core::option::Option::Some(v) => v,
+                    $crate::_core::option::Option::None => $crate::unreachable_unchecked!($crate::_core::concat!(
+                        "arithmetic overflow: ", $crate::_core::stringify!($($tt)+)
+                    )),
+                }
+            }
+            unchecked => { $crate::internal_ops!(@munch Unchecked; [] [] $($tt)+).into_inner() }
+        }
+    };
+}
+
+/// Rewrites an arithmetic expression by wrapping each operand in
+/// `$crate::ops::$wrap` while leaving its operators in place.
+///
+/// Every step takes an operand together with the operator ending it when it
+/// can, or a piece of one otherwise, as each step is a level of macro
+/// recursion and counts towards the caller's `recursion_limit`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! internal_ops {
+    // a binary operator ends the current operand
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] + $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) +] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] - $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) -] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] * $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) *] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] / $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) /] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] % $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) %] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] << $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) <<] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] >> $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) >>] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] & $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) &] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] | $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) |] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+] ^ $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)+) ^] [] $($rest)*)
+    };
+    // operators that aren't arithmetic
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] == $($rest:tt)*) => {
+        $crate::internal_ops!(@reject ==)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] != $($rest:tt)*) => {
+        $crate::internal_ops!(@reject !=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] < $($rest:tt)*) => {
+        $crate::internal_ops!(@reject <)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] > $($rest:tt)*) => {
+        $crate::internal_ops!(@reject >)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] <= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject <=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] >= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject >=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] && $($rest:tt)*) => {
+        $crate::internal_ops!(@reject &&)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] || $($rest:tt)*) => {
+        $crate::internal_ops!(@reject ||)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] = $($rest:tt)*) => {
+        $crate::internal_ops!(@reject =)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] += $($rest:tt)*) => {
+        $crate::internal_ops!(@reject +=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] -= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject -=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] *= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject *=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] /= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject /=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] %= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject %=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] <<= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject <<=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] >>= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject >>=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] &= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject &=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] |= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject |=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] ^= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject ^=)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] .. $($rest:tt)*) => {
+        $crate::internal_ops!(@reject ..)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] ..= $($rest:tt)*) => {
+        $crate::internal_ops!(@reject ..=)
+    };
+    // unary negation, before any operand
+    (@munch $wrap:ident; [$($out:tt)*] [] - $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* -] [] $($rest)*)
+    };
+    // a parenthesized operand may be a subexpression to rewrite
+    (@munch $wrap:ident; [$($out:tt)*] [] ($($group:tt)*) $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [@group ($($group)*)] $($rest)*)
+    };
+    // the last token of an operand and the operator ending it
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt + $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) +] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt - $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) -] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt * $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) *] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt / $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) /] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt % $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) %] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt << $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) <<] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt >> $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) >>] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt & $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) &] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt | $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) |] [] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt ^ $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)* $crate::internal_ops!(@operand $wrap; $($cur)* $tt) ^] [] $($rest)*)
+    };
+    // pieces of an operand that can't hold an operator
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] :: < $($generic:ty),+ > $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* :: < $($generic),+ >] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] :: $segment:tt $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* :: $segment] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] . $field:tt $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* . $field] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] as $ty:tt $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* as $ty] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $name:ident ($($args:tt)*) $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* $name ($($args)*)] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $name:ident [$($index:tt)*] $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* $name [$($index)*]] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)*] $tt:tt $($rest:tt)*) => {
+        $crate::internal_ops!(@munch $wrap; [$($out)*] [$($cur)* $tt] $($rest)*)
+    };
+    (@munch $wrap:ident; [$($out:tt)*] [$($cur:tt)+]) => {
+        $($out)* $crate::internal_ops!(@operand $wrap; $($cur)+)
     };
-}*/
+
+    (@operand $wrap:ident; @group ($($group:tt)*)) => {
+        $crate::internal_ops!(@munch $wrap; [] [] $($group)*)
+    };
+    (@operand $wrap:ident; @group $($cur:tt)+) => {
+        $crate::ops::$wrap::new($($cur)+)
+    };
+    (@operand $wrap:ident; $($cur:tt)+) => {
+        $crate::ops::$wrap::new($($cur)+)
+    };
+
+    (@reject $op:tt) => {
+        $crate::_core::compile_error!($crate::_core::concat!(
+            "`", $crate::_core::stringify!($op), "` isn't an arithmetic operator, keep it outside of `checked_ops!()` and `unchecked_ops!()`"
+        ))
+    };
+}
 
 #[doc(hidden)]
 pub use core as _core;
 
+pub mod symbol;
+pub mod registry;
+pub mod bisect;
+#[doc(hidden)]
+pub mod ops;
+
+#[cfg(any(test, feature = "std"))]
+pub mod baseline;
+#[cfg(any(test, feature = "std"))]
+pub mod build;
+#[cfg(any(test, feature = "std"))]
+pub mod demangle;
+#[cfg(any(test, feature = "std"))]
+pub mod dwarf;
+#[cfg(any(test, feature = "std"))]
+pub mod fallback;
+#[cfg(any(test, feature = "std"))]
+pub mod inventory;
+#[cfg(any(test, feature = "std"))]
+pub mod json;
+#[cfg(any(test, feature = "std"))]
+pub mod junit;
+#[cfg(any(test, feature = "std"))]
+pub mod ld;
+#[cfg(any(test, feature = "std"))]
+pub mod matrix;
+#[cfg(any(test, feature = "std"))]
+pub mod object;
+#[cfg(any(test, feature = "std"))]
+pub mod remarks;
+#[cfg(any(test, feature = "std"))]
+pub mod report;
+#[cfg(any(test, feature = "std"))]
+pub mod scope;
+#[cfg(any(test, feature = "std"))]
+pub mod testkit;
+#[cfg(any(test, feature = "std"))]
+pub mod toml;
+
+/// Compile-time variant of `Option::unwrap()` and `Result::unwrap()`
+///
+/// Fail to compile if the compiler can't prove the value is `Some` or `Ok`,
+/// like [`unreachable_static!`]. Unlike [`OptionExt::unwrap_static`], the site
+/// is expanded in the calling code, so link errors name the caller's location.
+///
+/// ```no_run
+/// # // Can't doctest this due to https://github.com/rust-lang/cargo/issues/4251
+/// let v = [1, 2, 3];
+/// let first = reachability::unwrap_static!(v.first());
+/// let parsed: u8 = reachability::expect_static!("7".parse(), "a digit");
+/// ```
+#[macro_export]
+macro_rules! unwrap_static {
+    ($value:expr $(,)?) => {
+        match $crate::IntoOption::into_option($value) {
+            $crate::_core::option::Option::Some(v) => v,
+            $crate::_core::option::Option::None => $crate::internal_unreachable_static_expr!($crate::_core::concat!(
+                "called `unwrap_static!()` on a `None` or `Err` value: ", $crate::_core::stringify!($value)
+            )),
+        }
+    };
+}
+
+/// Compile-time variant of `Option::expect()` and `Result::expect()`
+///
+/// See [`unwrap_static!`]. The message must be a string literal.
+#[macro_export]
+macro_rules! expect_static {
+    ($value:expr, $msg:literal $(,)?) => {
+        match $crate::IntoOption::into_option($value) {
+            $crate::_core::option::Option::Some(v) => v,
+            $crate::_core::option::Option::None => $crate::internal_unreachable_static_expr!($msg),
+        }
+    };
+}
+
+/// Compile-time variant of `Result::unwrap_err()`
+///
+/// See [`unwrap_static!`].
+#[macro_export]
+macro_rules! unwrap_err_static {
+    ($value:expr $(,)?) => {
+        match $value {
+            $crate::_core::result::Result::Err(e) => e,
+            $crate::_core::result::Result::Ok(_) => $crate::internal_unreachable_static_expr!($crate::_core::concat!(
+                "called `unwrap_err_static!()` on an `Ok` value: ", $crate::_core::stringify!($value)
+            )),
+        }
+    };
+}
+
+/// Compile-time variant of `Result::expect_err()`
+///
+/// See [`unwrap_static!`]. The message must be a string literal.
+#[macro_export]
+macro_rules! expect_err_static {
+    ($value:expr, $msg:literal $(,)?) => {
+        match $value {
+            $crate::_core::result::Result::Err(e) => e,
+            $crate::_core::result::Result::Ok(_) => $crate::internal_unreachable_static_expr!($msg),
+        }
+    };
+}
+
+/// `Option` or `Result`, for `unwrap_static!()`.
+#[doc(hidden)]
+pub trait IntoOption {
+    type Ok;
+
+    fn into_option(self) -> Option<Self::Ok>;
+}
+
+impl<T> IntoOption for Option<T> {
+    type Ok = T;
+
+    #[inline(always)]
+    fn into_option(self) -> Option<T> {
+        self
+    }
+}
+
+impl<T, E> IntoOption for Result<T, E> {
+    type Ok = T;
+
+    #[inline(always)]
+    fn into_option(self) -> Option<T> {
+        self.ok()
+    }
+}
+
+/// Unwrapping that must be proven or is unchecked in release builds.
+///
+/// ## Migration
+///
+/// `unwrap_unchecked()` was removed, as calls to it resolve to the inherent
+/// `Option::unwrap_unchecked` and `Result::unwrap_unchecked` methods in std,
+/// which silently drop the debug build panic. Use [`unwrap_debug_checked`]
+/// instead, or [`expect_debug_checked`] to include a message.
+/// `cargo reachability list --audit` finds the calls that still go to std's
+/// methods, along with the other unchecked uses lacking a justification.
+///
+/// [`unwrap_debug_checked`]: OptionExt::unwrap_debug_checked
+/// [`expect_debug_checked`]: OptionExt::expect_debug_checked
 pub trait OptionExt {
     type Ok;
 
+    /// Unwrap a value the compiler must prove is `Some` or `Ok`.
+    ///
+    /// See [`unreachable_static!`]. Every call shares the one site inside this
+    /// crate, so prefer [`unwrap_static!`] for link errors that name the
+    /// caller.
     fn unwrap_static(self) -> Self::Ok;
-    unsafe fn unwrap_unchecked(self) -> Self::Ok;
+
+    /// Unwrap a value, panicking on `None` or `Err` in debug builds.
+    ///
+    /// See [`unreachable_unchecked!`].
+    ///
+    /// # Safety
+    ///
+    /// `self` must not be `None` or `Err`.
+    unsafe fn unwrap_debug_checked(self) -> Self::Ok;
+
+    /// Unwrap a value, panicking with `msg` on `None` or `Err` in debug
+    /// builds.
+    ///
+    /// # Safety
+    ///
+    /// `self` must not be `None` or `Err`.
+    unsafe fn expect_debug_checked(self, msg: &str) -> Self::Ok;
 }
 
+/// Unwrapping of errors that must be proven or is unchecked in release
+/// builds.
+///
+/// ## Migration
+///
+/// `unwrap_err_unchecked()` was removed, as calls to it resolve to the
+/// inherent `Result::unwrap_err_unchecked` method in std. Use
+/// [`unwrap_err_debug_checked`] instead, or [`expect_err_debug_checked`] to
+/// include a message, and `cargo reachability list --audit` to find the
+/// calls that still go to std's method.
+///
+/// [`unwrap_err_debug_checked`]: ResultExt::unwrap_err_debug_checked
+/// [`expect_err_debug_checked`]: ResultExt::expect_err_debug_checked
 pub trait ResultExt {
     type Err;
 
+    /// Unwrap an error the compiler must prove is `Err`.
+    ///
+    /// See [`unreachable_static!`]. Prefer [`unwrap_err_static!`] for link
+    /// errors that name the caller.
     fn unwrap_err_static(self) -> Self::Err;
-    unsafe fn unwrap_err_unchecked(self) -> Self::Err;
+
+    /// Unwrap an error, panicking on `Ok` in debug builds.
+    ///
+    /// See [`unreachable_unchecked!`].
+    ///
+    /// # Safety
+    ///
+    /// `self` must not be `Ok`.
+    unsafe fn unwrap_err_debug_checked(self) -> Self::Err;
+
+    /// Unwrap an error, panicking with `msg` on `Ok` in debug builds.
+    ///
+    /// # Safety
+    ///
+    /// `self` must not be `Ok`.
+    unsafe fn expect_err_debug_checked(self, msg: &str) -> Self::Err;
 }
 
 impl<T> OptionExt for Option<T> {
@@ -147,9 +1191,20 @@ impl<T> OptionExt for Option<T> {
     }
 
     #[inline(always)]
-    unsafe fn unwrap_unchecked(self) -> T {
+    #[track_caller]
+    unsafe fn unwrap_debug_checked(self) -> T {
         match self {
-            None => unreachable_unchecked!(),
+            None => unreachable_unchecked!("called `unwrap_debug_checked()` on a `None` value"),
+            Some(v) => v,
+        }
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    #[cfg_attr(not(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked")))), allow(unused_variables))]
+    unsafe fn expect_debug_checked(self, msg: &str) -> T {
+        match self {
+            None => unreachable_unchecked!("{}", msg),
             Some(v) => v,
         }
     }
@@ -167,9 +1222,20 @@ impl<T, E> OptionExt for Result<T, E> {
     }
 
     #[inline(always)]
-    unsafe fn unwrap_unchecked(self) -> T {
+    #[track_caller]
+    unsafe fn unwrap_debug_checked(self) -> T {
         match self {
-            Err(_) => unreachable_unchecked!(),
+            Err(_) => unreachable_unchecked!("called `unwrap_debug_checked()` on an `Err` value"),
+            Ok(v) => v,
+        }
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    #[cfg_attr(not(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked")))), allow(unused_variables))]
+    unsafe fn expect_debug_checked(self, msg: &str) -> T {
+        match self {
+            Err(_) => unreachable_unchecked!("{}", msg),
             Ok(v) => v,
         }
     }
@@ -187,9 +1253,20 @@ impl<T, E> ResultExt for Result<T, E> {
     }
 
     #[inline(always)]
-    unsafe fn unwrap_err_unchecked(self) -> E {
+    #[track_caller]
+    unsafe fn unwrap_err_debug_checked(self) -> E {
         match self {
-            Ok(_) => unreachable_unchecked!(),
+            Ok(_) => unreachable_unchecked!("called `unwrap_err_debug_checked()` on an `Ok` value"),
+            Err(v) => v,
+        }
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    #[cfg_attr(not(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked")))), allow(unused_variables))]
+    unsafe fn expect_err_debug_checked(self, msg: &str) -> E {
+        match self {
+            Ok(_) => unreachable_unchecked!("{}", msg),
             Err(v) => v,
         }
     }
@@ -207,12 +1284,67 @@ pub mod tests {
 
     #[test]
     #[should_panic]
-    #[cfg(debug_assertions)]
+    #[cfg(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked"))))]
     fn unchecked_assert() {
         unsafe {
             unreachable_unchecked!("intentional");
         }
     }
 
+    #[test]
+    #[should_panic(expected = "called `unwrap_debug_checked()` on a `None` value")]
+    #[cfg(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked"))))]
+    fn unwrap_debug_checked() {
+        use crate::OptionExt;
+
+        unsafe {
+            assert_eq!(Some(grey_box(1)).unwrap_debug_checked(), 1);
+            None::<i32>.unwrap_debug_checked();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "intentional")]
+    #[cfg(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked"))))]
+    fn expect_err_debug_checked() {
+        use crate::ResultExt;
+
+        unsafe {
+            assert_eq!(Err::<(), _>(grey_box(1)).expect_err_debug_checked("unused"), 1);
+            Ok::<_, ()>(grey_box(1)).expect_err_debug_checked("intentional");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: grey_box(1) == 2")]
+    #[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+    fn assert_fallback() {
+        assert_static!(grey_box(1) == 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion `left matches right` failed: odd\n  left: 1\n right: 0 | 2")]
+    #[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+    fn assert_matches_fallback() {
+        assert_eq_static!(grey_box(1), 1);
+        assert_matches_static!(grey_box(1), 0 | 2, "odd");
+    }
+
+    #[test]
+    #[should_panic(expected = "called `unwrap_static!()` on a `None` or `Err` value: grey_box(1).checked_div(0)")]
+    #[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+    fn unwrap_fallback() {
+        assert_eq!(expect_static!(Ok::<_, ()>(grey_box(1)), "unused"), 1);
+        assert_eq!(unwrap_err_static!(Err::<(), _>(grey_box(1))), 1);
+        unwrap_static!(grey_box(1).checked_div(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "intentional {}")]
+    #[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+    fn expect_fallback() {
+        expect_err_static!(Ok::<_, ()>(grey_box(1)), "intentional {}");
+    }
+
     pub fn grey_box(v: i32) -> i32 { v }
 }
@@ -0,0 +1,618 @@
+//! Opt-level and LTO matrix builds for `cargo reachability matrix`.
+//!
+//! Whether a site is eliminated depends on the optimization settings, so the
+//! same permutations `release.nix` tests this crate with are run against any
+//! workspace: every opt-level crossed with every LTO mode, plus an unoptimized
+//! build with debug assertions where sites fall back to runtime checks. Each
+//! configuration is a `cargo build --release --bins --tests --keep-going` with
+//! the release profile overridden through `CARGO_PROFILE_RELEASE_*`, and every
+//! binary and test target is recorded as linked or not. lld stops after 20
+//! errors, so a configuration where it did is built again with
+//! `--error-limit=0` to list every site.
+
+use std::{env, fmt, fs, io};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use crate::json::{self, Value};
+use crate::ld::{self, Diagnostic};
+use crate::toml;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    O0,
+    O1,
+    O2,
+    O3,
+    Os,
+    Oz,
+}
+
+impl OptLevel {
+    pub const ALL: [OptLevel; 6] = [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::O3, OptLevel::Os, OptLevel::Oz];
+
+    /// The value of the `opt-level` profile setting.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OptLevel::O0 => "0",
+            OptLevel::O1 => "1",
+            OptLevel::O2 => "2",
+            OptLevel::O3 => "3",
+            OptLevel::Os => "s",
+            OptLevel::Oz => "z",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        OptLevel::ALL.iter().copied().find(|o| o.as_str() == s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lto {
+    Off,
+    Thin,
+    Fat,
+}
+
+impl Lto {
+    pub const ALL: [Lto; 3] = [Lto::Off, Lto::Thin, Lto::Fat];
+
+    /// The value of the `lto` profile setting.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lto::Off => "off",
+            Lto::Thin => "thin",
+            Lto::Fat => "fat",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "false" => Some(Lto::Off),
+            _ => Lto::ALL.iter().copied().find(|l| l.as_str() == s),
+        }
+    }
+}
+
+/// One configuration of the matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Profile {
+    pub opt_level: OptLevel,
+    pub lto: Lto,
+    pub debug_assertions: bool,
+}
+
+impl Profile {
+    /// The configurations `release.nix` builds.
+    pub fn matrix() -> Vec<Profile> {
+        let mut profiles: Vec<_> = OptLevel::ALL.iter()
+            .flat_map(|&opt_level| Lto::ALL.iter().map(move |&lto| Profile { opt_level, lto, debug_assertions: false }))
+            .collect();
+        profiles.push(Profile { opt_level: OptLevel::O0, lto: Lto::Off, debug_assertions: true });
+        profiles
+    }
+
+    /// Environment overriding cargo's release profile with this
+    /// configuration.
+    pub fn env(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("CARGO_PROFILE_RELEASE_OPT_LEVEL", self.opt_level.as_str()),
+            ("CARGO_PROFILE_RELEASE_LTO", self.lto.as_str()),
+            ("CARGO_PROFILE_RELEASE_DEBUG_ASSERTIONS", if self.debug_assertions { "true" } else { "false" }),
+            ("CARGO_PROFILE_RELEASE_INCREMENTAL", "false"),
+        ]
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "opt-level={} lto={}", self.opt_level.as_str(), self.lto.as_str())?;
+        if self.debug_assertions {
+            write!(f, " debug-assertions")?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of building one target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Linked,
+    /// The link failed, usually because of the listed sites.
+    LinkFailed(Vec<Diagnostic>),
+    /// The target failed to build before reaching the linker.
+    Failed(String),
+}
+
+impl Outcome {
+    pub fn linked(&self) -> bool {
+        *self == Outcome::Linked
+    }
+}
+
+/// The outcome of every target built in one configuration, in the order
+/// cargo reported them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Results {
+    pub profile: Profile,
+    pub targets: Vec<(String, Outcome)>,
+}
+
+/// Build all binary and test targets in one configuration. `cargo_args` are
+/// passed through to `cargo build`, for selecting packages and features.
+pub fn build(profile: Profile, cargo_args: &[OsString]) -> io::Result<Results> {
+    build_with(profile, cargo_args, &[]).map(|build| build.results)
+}
+
+/// What [`build_with`] collected from cargo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Build {
+    pub results: Results,
+    /// The paths of the executables that linked.
+    pub executables: Vec<PathBuf>,
+    /// The compiler's notes, such as the remarks of `-C remark`.
+    pub notes: Vec<String>,
+}
+
+/// [`build`] with extra environment for cargo.
+pub fn build_with(profile: Profile, cargo_args: &[OsString], envs: &[(&str, OsString)]) -> io::Result<Build> {
+    run(None, profile, cargo_args, envs)
+}
+
+/// [`build_with`] running cargo in `dir`, which decides the workspace and the
+/// `.cargo/config.toml` files that apply.
+pub fn build_in(dir: &Path, profile: Profile, cargo_args: &[OsString], envs: &[(&str, OsString)]) -> io::Result<Build> {
+    run(Some(dir), profile, cargo_args, envs)
+}
+
+/// Cargo arguments and environment adding `flags` to the rustflags cargo
+/// would build with in `dir`, or the current directory, given the `envs`
+/// it runs with on top of this process's.
+///
+/// Cargo only takes rustflags from the first of `CARGO_ENCODED_RUSTFLAGS`,
+/// `RUSTFLAGS`, `target.*.rustflags` and `build.rustflags` that is set, so
+/// setting another one would drop the user's flags. The flags are added to
+/// whichever is in use instead, through `--config` for the config files,
+/// whose arrays cargo merges.
+pub fn rustflags(dir: Option<&Path>, envs: &[(&str, OsString)], flags: &[&str]) -> (Vec<OsString>, Vec<(&'static str, OsString)>) {
+    let var = |name: &str| envs.iter().rev().find(|(k, _)| *k == name).map(|(_, v)| v.clone()).or_else(|| env::var_os(name));
+    if let Some(mut encoded) = var("CARGO_ENCODED_RUSTFLAGS") {
+        for flag in flags {
+            if !encoded.is_empty() {
+                encoded.push("\x1f");
+            }
+            encoded.push(flag);
+        }
+        return (Vec::new(), vec![("CARGO_ENCODED_RUSTFLAGS", encoded)]);
+    }
+    if let Some(mut rustflags) = var("RUSTFLAGS") {
+        for flag in flags {
+            rustflags.push(" ");
+            rustflags.push(flag);
+        }
+        return (Vec::new(), vec![("RUSTFLAGS", rustflags)]);
+    }
+
+    let dir = dir.map(Path::to_owned).or_else(|| env::current_dir().ok()).unwrap_or_default();
+    let table = if target_rustflags(&dir) { "target.'cfg(all())'" } else { "build" };
+    let flags: Vec<_> = flags.iter().map(|flag| format!("{:?}", flag)).collect();
+    (vec!["--config".into(), format!("{}.rustflags = [{}]", table, flags.join(", ")).into()], Vec::new())
+}
+
+/// Whether cargo takes its rustflags from `target.*.rustflags` in `dir`,
+/// where `build.rustflags` are ignored.
+fn target_rustflags(dir: &Path) -> bool {
+    let env = env::vars_os().any(|(name, _)| name.to_str()
+        .is_some_and(|name| name.starts_with("CARGO_TARGET_") && name.ends_with("_RUSTFLAGS")));
+    let home = env::var_os("CARGO_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    let mut files = dir.ancestors().map(|dir| dir.join(".cargo")).chain(home)
+        .flat_map(|dir| [dir.join("config.toml"), dir.join("config")]);
+    env || files.any(|path| {
+        let config = fs::read_to_string(path).ok().and_then(|contents| toml::parse(&contents).ok());
+        config.is_some_and(|config| config.get("target").is_some_and(|targets| {
+            targets.as_table().iter().any(|(_, target)| target.get("rustflags").is_some())
+        }))
+    })
+}
+
+/// Build, and build again with lld's error limit lifted if it stopped
+/// reporting the sites of a failed link.
+fn run(dir: Option<&Path>, profile: Profile, cargo_args: &[OsString], envs: &[(&str, OsString)]) -> io::Result<Build> {
+    let (build, error_limit) = run_cargo(dir, profile, cargo_args, envs)?;
+    let arg = match error_limit {
+        Some(arg) => arg,
+        None => return Ok(build),
+    };
+    let (args, flag_envs) = rustflags(dir, envs, &["-C", &format!("link-arg={}", arg)]);
+    let cargo_args: Vec<_> = cargo_args.iter().cloned().chain(args).collect();
+    let envs: Vec<_> = envs.iter().cloned().chain(flag_envs).collect();
+    run_cargo(dir, profile, &cargo_args, &envs).map(|(build, _)| build)
+}
+
+fn run_cargo(dir: Option<&Path>, profile: Profile, cargo_args: &[OsString], envs: &[(&str, OsString)]) -> io::Result<(Build, Option<&'static str>)> {
+    let mut command = Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let mut child = command
+        .args(["build", "--release", "--bins", "--tests", "--keep-going", "--message-format=json"])
+        .args(cargo_args)
+        .envs(profile.env())
+        .envs(envs.iter().map(|(k, v)| (k, v)))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Read stderr alongside stdout so neither pipe fills up
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr = thread::spawn(move || {
+        let mut output = String::new();
+        stderr.read_to_string(&mut output).map(|_| output)
+    });
+
+    let mut results = Results { profile, targets: Vec::new() };
+    let mut executables = Vec::new();
+    let mut notes = Vec::new();
+    let mut index = HashMap::new();
+    let mut error_limit = None;
+    for line in io::BufReader::new(child.stdout.take().unwrap()).lines() {
+        let message = match json::parse(&line?) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if let Some(Value::String(path)) = message.get("executable") {
+            executables.push(PathBuf::from(path));
+        }
+        if let Some(note) = parse_note(&message) {
+            notes.push(note.to_owned());
+        }
+        error_limit = error_limit.or_else(|| parse_error_limit(&message));
+        let (label, outcome) = match parse_message(&message) {
+            Some(result) => result,
+            None => continue,
+        };
+        let i = *index.entry(label.clone()).or_insert_with(|| {
+            results.targets.push((label, Outcome::Linked));
+            results.targets.len() - 1
+        });
+        // A link failure outranks other errors, which outrank success
+        let existing = &mut results.targets[i].1;
+        match (&*existing, &outcome) {
+            (Outcome::LinkFailed(_), _) | (Outcome::Failed(_), Outcome::Linked) => (),
+            _ => *existing = outcome,
+        }
+    }
+    let status = child.wait()?;
+    let stderr = stderr.join().unwrap_or_else(|_| Ok(String::new()))?;
+
+    // `--keep-going` fails on link failures, but cargo can also fail before
+    // building anything, such as on an unknown feature or a missing lockfile
+    let failed = results.targets.iter().any(|(_, outcome)| !outcome.linked());
+    if results.targets.is_empty() || (!status.success() && !failed) {
+        let error = match cargo_error(&stderr) {
+            "" if status.success() => "no binary or test targets were built".to_owned(),
+            "" => format!("cargo failed with {}", status),
+            error => error.to_owned(),
+        };
+        return Err(io::Error::other(error));
+    }
+
+    Ok((Build { results, executables, notes }, error_limit))
+}
+
+/// The errors in cargo's stderr, without the progress lines before them or
+/// the first `error: `.
+fn cargo_error(stderr: &str) -> &str {
+    let start = stderr.find("error").unwrap_or(stderr.len());
+    let error = stderr[start..].trim();
+    error.strip_prefix("error: ").unwrap_or(error)
+}
+
+/// `rustc --version` for the toolchain cargo builds with, without the
+/// leading `rustc`.
+pub fn rustc_version() -> io::Result<String> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc).arg("--version").output()?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    Ok(version.trim().trim_start_matches("rustc ").to_owned())
+}
+
+fn parse_note(message: &Value) -> Option<&str> {
+    let message = message.get("message")?;
+    match message.get("level")?.as_str()? {
+        "note" => message.get("message")?.as_str(),
+        _ => None,
+    }
+}
+
+/// The linker argument lifting lld's error limit, if `message` is a failed
+/// link lld stopped reporting errors for.
+fn parse_error_limit(message: &Value) -> Option<&'static str> {
+    let rendered = message.get("message")?.get("rendered")?.as_str()?;
+    let start = rendered.find("linking with `")? + "linking with `".len();
+    let linker = rendered[start..].split('`').next()?;
+    ld::error_limit_arg(linker.as_ref(), rendered)
+}
+
+fn parse_message(message: &Value) -> Option<(String, Outcome)> {
+    let target = message.get("target")?;
+    let name = target.get("name")?.as_str()?;
+    let kind = target.get("kind")?.as_array().first()?.as_str()?;
+    let label = match kind {
+        "test" | "bin" => name.to_owned(),
+        _ => format!("{} ({})", name, kind),
+    };
+
+    match message.get("reason")?.as_str()? {
+        "compiler-artifact" => match message.get("executable") {
+            Some(Value::String(_)) => Some((label, Outcome::Linked)),
+            _ => None,
+        },
+        "compiler-message" => {
+            let message = message.get("message")?;
+            if message.get("level")?.as_str()? != "error" {
+                return None;
+            }
+            let rendered = message.get("rendered")?.as_str()?;
+            let outcome = match rendered.contains("error: linking with") {
+                true => Outcome::LinkFailed(ld::diagnostics(rendered)),
+                false => Outcome::Failed(rendered.lines().next().unwrap_or("").into()),
+            };
+            Some((label, outcome))
+        },
+        _ => None,
+    }
+}
+
+/// The configurations targets are expected to link in. Every target links
+/// with debug assertions, where sites fall back to runtime checks, and
+/// targets without entries link in every configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expectations {
+    /// Targets expected to fail whenever sites are statically enforced.
+    pub fail: Vec<String>,
+    /// Configurations targets link in, and only those for targets with
+    /// entries.
+    pub links: Vec<ExpectLink>,
+}
+
+/// Configurations a target is expected to link in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectLink {
+    pub target: String,
+    /// The opt-levels the entry applies to, or every opt-level if empty.
+    pub opt_levels: Vec<OptLevel>,
+    /// The LTO modes the entry applies to, or every mode if empty.
+    pub ltos: Vec<Lto>,
+}
+
+impl ExpectLink {
+    /// Parse `<target>:<opt-levels>[:<LTO modes>]`, with comma separated
+    /// lists that are empty for every opt-level or mode, as in
+    /// `lto:2,3,s,z:fat`.
+    pub fn parse(s: &str) -> Option<Self> {
+        fn list<T>(s: &str, parse: fn(&str) -> Option<T>) -> Option<Vec<T>> {
+            s.split(',').map(str::trim).filter(|v| !v.is_empty()).map(parse).collect()
+        }
+        let mut parts = s.split(':');
+        let target = parts.next().filter(|t| !t.is_empty())?;
+        let opt_levels = list(parts.next()?, OptLevel::parse)?;
+        let ltos = list(parts.next().unwrap_or(""), Lto::parse)?;
+        match parts.next() {
+            Some(_) => None,
+            None => Some(ExpectLink { target: target.into(), opt_levels, ltos }),
+        }
+    }
+
+    /// Whether the entry applies to `profile`.
+    pub fn applies(&self, profile: &Profile) -> bool {
+        (self.opt_levels.is_empty() || self.opt_levels.contains(&profile.opt_level))
+            && (self.ltos.is_empty() || self.ltos.contains(&profile.lto))
+    }
+}
+
+impl Expectations {
+    /// Whether `target` is expected to link in `profile`.
+    pub fn link(&self, profile: &Profile, target: &str) -> bool {
+        if profile.debug_assertions {
+            return true;
+        }
+        if self.fail.iter().any(|t| t == target) {
+            return false;
+        }
+        let mut links = self.links.iter().filter(|l| l.target == target).peekable();
+        links.peek().is_none() || links.any(|l| l.applies(profile))
+    }
+}
+
+/// A table of results with one row per configuration and one column per
+/// target.
+pub struct Table<'a> {
+    pub results: &'a [Results],
+    pub expectations: &'a Expectations,
+}
+
+impl Table<'_> {
+    pub fn targets(&self) -> Vec<&str> {
+        let mut targets = Vec::new();
+        for (target, _) in self.results.iter().flat_map(|r| &r.targets) {
+            if !targets.contains(&&target[..]) {
+                targets.push(target);
+            }
+        }
+        targets
+    }
+
+    /// Whether every target linked, or failed to link, as expected in every
+    /// configuration. A target missing from a configuration, such as when
+    /// cargo stopped early, fails it.
+    pub fn passed(&self) -> bool {
+        let targets = self.targets();
+        self.results.iter().all(|r| targets.iter().all(|target| {
+            match r.targets.iter().find(|(t, _)| t == target).map(|(_, o)| o) {
+                None | Some(Outcome::Failed(_)) => false,
+                Some(outcome) => outcome.linked() == self.expectations.link(&r.profile, target),
+            }
+        }))
+    }
+}
+
+impl fmt::Display for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let targets = self.targets();
+        let labels: Vec<_> = self.results.iter().map(|r| r.profile.to_string()).collect();
+        let width = labels.iter().map(String::len).max().unwrap_or(0);
+
+        let mut row = format!("{:width$}", "", width = width);
+        for target in &targets {
+            row.push_str(&format!("  {:5}", target));
+        }
+        write!(f, "{}", row.trim_end())?;
+
+        for (label, results) in labels.iter().zip(self.results) {
+            let mut row = format!("{:width$}", label, width = width);
+            for target in &targets {
+                let outcome = results.targets.iter().find(|(t, _)| t == target).map(|(_, o)| o);
+                let expected = self.expectations.link(&results.profile, target);
+                let cell = match (outcome, expected) {
+                    (None, _) => "-",
+                    (Some(Outcome::Linked), true) => "ok",
+                    (Some(Outcome::Linked), false) => "XPASS",
+                    (Some(Outcome::LinkFailed(_)), true) => "FAIL",
+                    (Some(Outcome::LinkFailed(_)), false) => "xfail",
+                    (Some(Outcome::Failed(_)), _) => "ERROR",
+                };
+                row.push_str(&format!("  {:width$}", cell, width = target.len().max(5)));
+            }
+            write!(f, "\n{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles() {
+        let matrix = Profile::matrix();
+        assert_eq!(matrix.len(), 19);
+        assert_eq!(matrix[5].to_string(), "opt-level=1 lto=fat");
+        assert_eq!(matrix[18].to_string(), "opt-level=0 lto=off debug-assertions");
+        assert_eq!(Lto::parse("false"), Some(Lto::Off));
+        assert_eq!(OptLevel::parse("s"), Some(OptLevel::Os));
+    }
+
+    #[test]
+    fn messages() {
+        let artifact = json::parse(r#"{"reason":"compiler-artifact","target":{"kind":["test"],"name":"opt1"},"executable":"/t/opt1"}"#).unwrap();
+        assert_eq!(parse_message(&artifact), Some(("opt1".into(), Outcome::Linked)));
+
+        let lib = json::parse(r#"{"reason":"compiler-artifact","target":{"kind":["lib"],"name":"app"},"executable":null}"#).unwrap();
+        assert_eq!(parse_message(&lib), None);
+
+        let link = json::parse(r#"{"reason":"compiler-message","target":{"kind":["lib"],"name":"app"},"message":{"level":"error",
+            "rendered":"error: linking with `cc` failed\n  = note: rust-lld: error: undefined symbol: ___unreachable_static___@app@src/lib.rs:3:5\n"}}"#).unwrap();
+        match parse_message(&link) {
+            Some((label, Outcome::LinkFailed(diagnostics))) => {
+                assert_eq!(label, "app (lib)");
+                assert_eq!(diagnostics[0].site().unwrap().line, 3);
+            },
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn cargo_errors() {
+        let stderr = "    Updating crates.io index\nerror: the package 'app' does not contain this feature: nope\n";
+        assert_eq!(cargo_error(stderr), "the package 'app' does not contain this feature: nope");
+        assert_eq!(cargo_error("    Finished `release` profile\n"), "");
+    }
+
+    #[test]
+    fn extra_rustflags() {
+        let flags = ["-C", "link-arg=-Wl,--error-limit=0"];
+        let (args, envs) = rustflags(None, &[("CARGO_ENCODED_RUSTFLAGS", "--cfg\x1ftokio_unstable".into())], &flags);
+        assert!(args.is_empty());
+        assert_eq!(envs, [("CARGO_ENCODED_RUSTFLAGS", OsString::from("--cfg\x1ftokio_unstable\x1f-C\x1flink-arg=-Wl,--error-limit=0"))]);
+        let (_, envs) = rustflags(None, &[("CARGO_ENCODED_RUSTFLAGS", "".into())], &flags);
+        assert_eq!(envs[0].1, "-C\x1flink-arg=-Wl,--error-limit=0");
+
+        let dir = env::temp_dir().join(format!("reachability-rustflags-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".cargo")).unwrap();
+        fs::write(dir.join(".cargo/config.toml"), "[target.'cfg(unix)']\nrustflags = [\"-Ctarget-cpu=native\"]\n").unwrap();
+        let target = target_rustflags(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(target);
+
+        let link = json::parse(r#"{"reason":"compiler-message","target":{"kind":["bin"],"name":"app"},"message":{"level":"error",
+            "rendered":"error: linking with `cc` failed: exit status: 1\n  = note: rust-lld: error: too many errors emitted, stopping now (use --error-limit=0 to see all errors)\n"}}"#).unwrap();
+        assert_eq!(parse_error_limit(&link), Some("-Wl,--error-limit=0"));
+    }
+
+    #[test]
+    fn table() {
+        let profiles = Profile::matrix();
+        let results = [
+            Results { profile: profiles[0], targets: vec![
+                ("opt1".into(), Outcome::LinkFailed(Vec::new())),
+                ("opt2".into(), Outcome::LinkFailed(Vec::new())),
+                ("fail".into(), Outcome::LinkFailed(Vec::new())),
+            ] },
+            Results { profile: profiles[18], targets: vec![
+                ("opt1".into(), Outcome::Linked),
+                ("opt2".into(), Outcome::Linked),
+                ("fail".into(), Outcome::Linked),
+            ] },
+        ];
+        let expectations = Expectations { fail: vec!["fail".into()], links: vec![ExpectLink::parse("opt2:2,3,s,z").unwrap()] };
+        let table = Table { results: &results, expectations: &expectations };
+        assert_eq!(table.to_string(), [
+            "                                      opt1   opt2   fail",
+            "opt-level=0 lto=off                   FAIL   xfail  xfail",
+            "opt-level=0 lto=off debug-assertions  ok     ok     ok",
+        ].join("\n"));
+        assert!(!table.passed());
+
+        let results = [
+            Results { profile: profiles[0], targets: vec![("fail".into(), Outcome::LinkFailed(Vec::new()))] },
+            Results { profile: profiles[18], targets: vec![
+                ("opt1".into(), Outcome::Linked),
+                ("fail".into(), Outcome::Linked),
+            ] },
+        ];
+        let table = Table { results: &results, expectations: &expectations };
+        assert!(!table.passed());
+        assert!(Table { results: &results[1..], expectations: &expectations }.passed());
+    }
+
+    #[test]
+    fn expectations() {
+        let lto = ExpectLink::parse("lto:1,2,3,s,z:thin").unwrap();
+        assert_eq!(lto, ExpectLink { target: "lto".into(), opt_levels: vec![OptLevel::O1, OptLevel::O2, OptLevel::O3, OptLevel::Os, OptLevel::Oz], ltos: vec![Lto::Thin] });
+        assert_eq!(ExpectLink::parse("lto::fat").unwrap().opt_levels, []);
+        assert_eq!(ExpectLink::parse("lto"), None);
+        assert_eq!(ExpectLink::parse("lto:4"), None);
+        assert_eq!(ExpectLink::parse("lto:1:thin:fat"), None);
+
+        let expectations = Expectations { fail: vec!["fail".into()], links: vec![lto, ExpectLink::parse("lto:2,3,s,z:fat").unwrap()] };
+        let profiles = Profile::matrix();
+        let links: Vec<_> = profiles.iter().map(|p| expectations.link(p, "lto")).collect();
+        assert_eq!(links, [
+            false, false, false,
+            false, true, false,
+            false, true, true,
+            false, true, true,
+            false, true, true,
+            false, true, true,
+            true,
+        ]);
+        assert!(profiles.iter().all(|p| expectations.link(p, "opt1")));
+        assert!(profiles.iter().all(|p| expectations.link(p, "fail") == p.debug_assertions));
+    }
+}
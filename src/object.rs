@@ -0,0 +1,694 @@
+//! Object file scanning for `cargo reachability scan`.
+//!
+//! Every `unreachable_static!()` site that survives optimization leaves a
+//! relocation against its marker symbol in the object file rustc emits, long
+//! before the final link fails on it. Scanning ELF objects, rlibs (`ar`
+//! archives of objects) and linked executables for those relocations tells
+//! which sites survived in each crate, and which function each is in.
+//!
+//! Relocations are attributed to the function symbol covering their offset,
+//! and to the chain of functions inlined there when the file has debug info
+//! (see [`crate::dwarf`]). Linked files only keep their relocations when linked with `--emit-relocs`
+//! (`-C link-arg=-Wl,--emit-relocs`); otherwise only the marker symbols
+//! themselves are found, without a function.
+
+use std::{fs, io};
+use std::convert::{TryFrom, TryInto};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::demangle::demangle;
+use crate::dwarf::{DebugSection, Dwarf, Frame};
+use crate::ld::Diagnostic;
+use crate::{registry, symbol};
+
+/// A reference to an `unreachable_static!()` marker symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The archive member containing the reference, when scanning an archive.
+    pub member: Option<String>,
+    /// The marker's link name.
+    pub symbol: String,
+    /// The demangled name of the function containing the reference, if known.
+    pub function: Option<String>,
+    /// The section containing the reference, if known.
+    pub section: Option<String>,
+    /// The offset of the reference in its section for relocatable objects,
+    /// or its virtual address for linked files.
+    pub address: u64,
+    /// The functions inlined at the reference, innermost first, when the
+    /// file has debug info.
+    pub frames: Vec<Frame>,
+}
+
+/// Scan an ELF file or an archive of ELF files for marker references.
+///
+/// Archive members that aren't ELF files, like the metadata in rlibs, are
+/// skipped.
+pub fn scan(data: &[u8]) -> io::Result<Vec<Reference>> {
+    if let Some(members) = data.strip_prefix(b"!<arch>\n") {
+        let mut references = Vec::new();
+        for (name, data) in archive_members(members)? {
+            if !data.starts_with(ELF_MAGIC) {
+                continue;
+            }
+            for mut reference in Elf::parse(data)?.references()? {
+                reference.member = Some(name.clone());
+                references.push(reference);
+            }
+        }
+        Ok(references)
+    } else if data.starts_with(ELF_MAGIC) {
+        Elf::parse(data)?.references()
+    } else {
+        Err(invalid("not an ELF file or archive"))
+    }
+}
+
+/// Read and [`scan`] the file at `path`.
+pub fn scan_file(path: impl AsRef<Path>) -> io::Result<Vec<Reference>> {
+    scan(&fs::read(path)?)
+}
+
+/// The sites registered by `warn` builds (see [`crate::registry`]) in an ELF
+/// file or an archive of ELF files.
+pub fn registered_sites(data: &[u8]) -> io::Result<Vec<String>> {
+    let mut sites = Vec::new();
+    let mut scan = |data: &[u8]| -> io::Result<()> {
+        let elf = Elf::parse(data)?;
+        for section in elf.sections.iter().filter(|s| s.name == registry::SECTION) {
+            for symbol in registry::symbols(elf.section_data(section)?) {
+                if !sites.iter().any(|s| s == symbol) {
+                    sites.push(symbol.to_owned());
+                }
+            }
+        }
+        Ok(())
+    };
+
+    if let Some(members) = data.strip_prefix(b"!<arch>\n") {
+        for (_, data) in archive_members(members)? {
+            if data.starts_with(ELF_MAGIC) {
+                scan(data)?;
+            }
+        }
+    } else if data.starts_with(ELF_MAGIC) {
+        scan(data)?;
+    } else {
+        return Err(invalid("not an ELF file or archive"));
+    }
+    Ok(sites)
+}
+
+/// Group references by site, in the order they were found. The references
+/// can come from several files, with the link names of different targets.
+pub fn diagnostics<'a>(references: impl IntoIterator<Item = &'a Reference>) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for reference in references {
+        let index = match diagnostics.iter().position(|d| symbol::same_site(&d.symbol, &reference.symbol)) {
+            Some(index) => index,
+            None => {
+                diagnostics.push(Diagnostic { symbol: reference.symbol.clone(), functions: Vec::new() });
+                diagnostics.len() - 1
+            },
+        };
+        let functions = &mut diagnostics[index].functions;
+        if let Some(function) = &reference.function {
+            if !functions.contains(function) {
+                functions.push(function.clone());
+            }
+        }
+    }
+    diagnostics
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The members of an `ar` archive, after its magic.
+fn archive_members(mut data: &[u8]) -> io::Result<Vec<(String, &[u8])>> {
+    let mut members = Vec::new();
+    let mut long_names: &[u8] = &[];
+    while data.len() >= 60 {
+        let (header, rest) = data.split_at(60);
+        let field = |range: std::ops::Range<usize>| String::from_utf8_lossy(&header[range]).trim().to_owned();
+        let size: usize = field(48..58).parse().map_err(|_| invalid("malformed archive member size"))?;
+        let member = rest.get(..size).ok_or_else(|| invalid("truncated archive member"))?;
+        data = rest.get(size + size % 2..).unwrap_or(&[]);
+
+        let name = field(0..16);
+        let (name, member) = match &name[..] {
+            // GNU symbol table and long name table
+            "/" | "/SYM64/" | "__.SYMDEF" | "__.SYMDEF SORTED" => continue,
+            "//" => {
+                long_names = member;
+                continue;
+            },
+            _ => if let Some(len) = name.strip_prefix("#1/") {
+                // BSD: the name precedes the member's data
+                let len: usize = len.parse().map_err(|_| invalid("malformed archive member name"))?;
+                let name = member.get(..len).ok_or_else(|| invalid("truncated archive member"))?;
+                let name = String::from_utf8_lossy(name).trim_end_matches('\0').to_owned();
+                (name, &member[len..])
+            } else if let Some(offset) = name.strip_prefix('/').and_then(|o| o.parse::<usize>().ok()) {
+                let name = long_names.get(offset..).ok_or_else(|| invalid("malformed archive member name"))?;
+                let end = name.iter().position(|&b| b == b'\n').unwrap_or(name.len());
+                let name = String::from_utf8_lossy(&name[..end]);
+                (name.strip_suffix('/').unwrap_or(&name).to_owned(), member)
+            } else {
+                (name.strip_suffix('/').unwrap_or(&name).to_owned(), member)
+            },
+        };
+        members.push((name, member));
+    }
+    Ok(members)
+}
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+const ET_REL: u16 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHT_REL: u32 = 9;
+const SHT_DYNSYM: u32 = 11;
+const SHT_SYMTAB_SHNDX: u32 = 18;
+const SHF_ALLOC: u64 = 2;
+const SHN_UNDEF: u16 = 0;
+const SHN_LORESERVE: u16 = 0xff00;
+const SHN_XINDEX: u16 = 0xffff;
+const STT_FUNC: u8 = 2;
+
+/// A section header.
+#[derive(Debug, Clone)]
+pub(crate) struct Section {
+    pub name: String,
+    pub kind: u32,
+    pub flags: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+}
+
+/// A symbol table entry.
+#[derive(Debug, Clone)]
+pub(crate) struct Symbol {
+    pub name: String,
+    pub kind: u8,
+    /// The section index, with `SHN_XINDEX` resolved.
+    pub section: u32,
+    pub value: u64,
+    pub size: u64,
+}
+
+impl Symbol {
+    fn is_defined(&self) -> bool {
+        self.section != SHN_UNDEF as u32
+    }
+}
+
+/// A relocation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Relocation {
+    pub offset: u64,
+    pub kind: u32,
+    pub symbol: usize,
+    pub addend: i64,
+}
+
+/// A parsed ELF file of either class and byte order.
+pub(crate) struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    is_le: bool,
+    machine: u16,
+    pub relocatable: bool,
+    pub sections: Vec<Section>,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> io::Result<Self> {
+        let mut elf = Elf {
+            data,
+            is_64: data.get(4) == Some(&2),
+            is_le: data.get(5) == Some(&1),
+            machine: 0,
+            relocatable: false,
+            sections: Vec::new(),
+        };
+        if !data.starts_with(ELF_MAGIC) {
+            return Err(invalid("not an ELF file"));
+        }
+        elf.relocatable = elf.u16(16)? == ET_REL;
+        elf.machine = elf.u16(18)?;
+
+        let (shoff, shentsize, shnum, shstrndx) = match elf.is_64 {
+            true => (elf.u64(0x28)?, elf.u16(0x3a)?, elf.u16(0x3c)?, elf.u16(0x3e)?),
+            false => (elf.u32(0x20)? as u64, elf.u16(0x2e)?, elf.u16(0x30)?, elf.u16(0x32)?),
+        };
+        if shoff == 0 {
+            return Ok(elf);
+        }
+        let header = |i: u64| i.checked_mul(shentsize as u64).and_then(|h| h.checked_add(shoff))
+            .filter(|&h| h < data.len() as u64)
+            .ok_or_else(|| invalid("truncated ELF section headers"));
+        // Files with too many sections for the header keep the counts in
+        // section 0
+        let mut shnum = shnum as u64;
+        let mut shstrndx = shstrndx as u32;
+        if shnum == 0 {
+            shnum = elf.word(header(0)? + if elf.is_64 { 32 } else { 20 })?;
+        }
+        if shstrndx == SHN_XINDEX as u32 {
+            shstrndx = elf.u32(header(0)? + if elf.is_64 { 40 } else { 24 })?;
+        }
+
+        let mut names = Vec::new();
+        for i in 0..shnum {
+            let h = header(i)?;
+            let section = match elf.is_64 {
+                true => Section {
+                    name: String::new(),
+                    kind: elf.u32(h + 4)?,
+                    flags: elf.u64(h + 8)?,
+                    offset: elf.u64(h + 24)?,
+                    size: elf.u64(h + 32)?,
+                    link: elf.u32(h + 40)?,
+                    info: elf.u32(h + 44)?,
+                },
+                false => Section {
+                    name: String::new(),
+                    kind: elf.u32(h + 4)?,
+                    flags: elf.u32(h + 8)? as u64,
+                    offset: elf.u32(h + 16)? as u64,
+                    size: elf.u32(h + 20)? as u64,
+                    link: elf.u32(h + 24)?,
+                    info: elf.u32(h + 28)?,
+                },
+            };
+            names.push(elf.u32(h)?);
+            elf.sections.push(section);
+        }
+        if let Some(strtab) = elf.sections.get(shstrndx as usize).map(|s| elf.section_data(s)).transpose()? {
+            for (section, name) in elf.sections.iter_mut().zip(names) {
+                section.name = string(strtab, name as usize);
+            }
+        }
+        Ok(elf)
+    }
+
+    fn bytes<const N: usize>(&self, offset: u64) -> io::Result<[u8; N]> {
+        let offset = usize::try_from(offset).map_err(|_| invalid("truncated ELF file"))?;
+        let mut bytes: [u8; N] = self.data.get(offset..offset.saturating_add(N))
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| invalid("truncated ELF file"))?;
+        if !self.is_le {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    pub fn u16(&self, offset: u64) -> io::Result<u16> {
+        self.bytes(offset).map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&self, offset: u64) -> io::Result<u32> {
+        self.bytes(offset).map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&self, offset: u64) -> io::Result<u64> {
+        self.bytes(offset).map(u64::from_le_bytes)
+    }
+
+    /// A class sized word.
+    pub fn word(&self, offset: u64) -> io::Result<u64> {
+        match self.is_64 {
+            true => self.u64(offset),
+            false => self.u32(offset).map(u64::from),
+        }
+    }
+
+    pub fn section_data(&self, section: &Section) -> io::Result<&'a [u8]> {
+        // Such as `.bss`, which has no data in the file
+        if section.kind == SHT_NOBITS {
+            return Ok(&[]);
+        }
+        let start = usize::try_from(section.offset).ok();
+        let end = start.zip(usize::try_from(section.size).ok()).and_then(|(s, n)| s.checked_add(n));
+        start.zip(end)
+            .and_then(|(start, end)| self.data.get(start..end))
+            .ok_or_else(|| invalid("truncated ELF section"))
+    }
+
+    /// The entries of the symbol table in section `index`.
+    pub fn symbols(&self, index: usize) -> io::Result<Vec<Symbol>> {
+        let table = &self.sections[index];
+        let strtab = self.sections.get(table.link as usize)
+            .ok_or_else(|| invalid("malformed ELF symbol table"))?;
+        let strtab = self.section_data(strtab)?;
+        let shndx = self.sections.iter()
+            .find(|s| s.kind == SHT_SYMTAB_SHNDX && s.link as usize == index);
+        // Keeps the offsets below within the file
+        self.section_data(table)?;
+        if let Some(shndx) = shndx {
+            self.section_data(shndx)?;
+        }
+
+        let size = if self.is_64 { 24 } else { 16 };
+        let mut symbols = Vec::new();
+        for i in 0..table.size / size {
+            let s = table.offset + i * size;
+            let (info, section, value, size) = match self.is_64 {
+                true => (self.bytes::<1>(s + 4)?[0], self.u16(s + 6)?, self.u64(s + 8)?, self.u64(s + 16)?),
+                false => (self.bytes::<1>(s + 12)?[0], self.u16(s + 14)?, self.u32(s + 4)? as u64, self.u32(s + 8)? as u64),
+            };
+            let section = match (section, shndx) {
+                (SHN_XINDEX, Some(shndx)) => self.u32(shndx.offset + i * 4)?,
+                // Absolute and common symbols aren't in any section
+                (section, _) if section >= SHN_LORESERVE => u32::MAX,
+                (section, _) => section as u32,
+            };
+            symbols.push(Symbol {
+                name: string(strtab, self.u32(s)? as usize),
+                kind: info & 0xf,
+                section,
+                value,
+                size,
+            });
+        }
+        Ok(symbols)
+    }
+
+    /// The entries of the `SHT_REL` or `SHT_RELA` section `section`.
+    pub fn relocations(&self, section: &Section) -> io::Result<Vec<Relocation>> {
+        let rela = section.kind == SHT_RELA;
+        let size = match (self.is_64, rela) {
+            (true, true) => 24,
+            (true, false) => 16,
+            (false, true) => 12,
+            (false, false) => 8,
+        };
+        let word = if self.is_64 { 8 } else { 4 };
+        // Keeps the offsets below within the file
+        self.section_data(section)?;
+        let mut relocations = Vec::new();
+        for i in 0..section.size / size {
+            let r = section.offset + i * size;
+            let info = self.word(r + word)?;
+            let (symbol, kind) = match self.is_64 {
+                true => (info >> 32, info as u32),
+                false => (info >> 8, info as u32 & 0xff),
+            };
+            let addend = match (rela, self.is_64) {
+                (false, _) => 0,
+                (true, true) => self.u64(r + 16)? as i64,
+                (true, false) => self.u32(r + 8)? as i32 as i64,
+            };
+            relocations.push(Relocation { offset: self.word(r)?, kind, symbol: symbol as usize, addend });
+        }
+        Ok(relocations)
+    }
+
+    /// Every marker reference in allocated sections, and every marker symbol
+    /// without one. Markers are only defined in fallback builds (see
+    /// [`crate::fallback`]).
+    fn references(&self) -> io::Result<Vec<Reference>> {
+        let mut references = Vec::new();
+        let mut addresses = Vec::new();
+        let mut symbol_tables = Vec::new();
+        for (index, table) in self.sections.iter().enumerate() {
+            if table.kind == SHT_SYMTAB || table.kind == SHT_DYNSYM {
+                symbol_tables.push((index, self.symbols(index)?));
+            }
+        }
+        let functions: Vec<&Symbol> = symbol_tables.iter()
+            .filter(|(index, _)| self.sections[*index].kind == SHT_SYMTAB)
+            .flat_map(|(_, symbols)| symbols.iter().filter(|s| s.kind == STT_FUNC && s.is_defined()))
+            .collect();
+
+        for section in &self.sections {
+            if section.kind != SHT_RELA && section.kind != SHT_REL {
+                continue;
+            }
+            // Dynamic relocations have no target section
+            let target = Some(section.info as usize).filter(|&i| i != 0).and_then(|i| self.sections.get(i));
+            if target.is_some_and(|t| t.flags & SHF_ALLOC == 0) {
+                continue;
+            }
+            let symbols = match symbol_tables.iter().find(|(index, _)| *index == section.link as usize) {
+                Some((_, symbols)) => symbols,
+                None => continue,
+            };
+            for relocation in self.relocations(section)? {
+                let symbol = match symbols.get(relocation.symbol) {
+                    Some(symbol) if symbol::is_marker(&symbol.name) => symbol,
+                    _ => continue,
+                };
+                let function = functions.iter().find(|f| match self.relocatable {
+                    true => f.section == section.info,
+                    false => true,
+                } && f.value.checked_add(f.size.max(1)).is_some_and(|end| (f.value..end).contains(&relocation.offset)));
+                let function = function.map(|f| &f.name[..])
+                    .or_else(|| target.and_then(|t| t.name.strip_prefix(".text.")).filter(|_| self.relocatable));
+                references.push(Reference {
+                    member: None,
+                    symbol: symbol.name.clone(),
+                    function: function.map(|f| demangle(f).into_owned()),
+                    section: target.map(|t| t.name.clone()),
+                    address: relocation.offset,
+                    frames: Vec::new(),
+                });
+                addresses.push((if self.relocatable { section.info } else { 0 }, relocation.offset));
+            }
+        }
+
+        if !addresses.is_empty() {
+            let frames = self.dwarf(&symbol_tables)?.frames(&addresses);
+            for (reference, frames) in references.iter_mut().zip(frames) {
+                reference.frames = frames;
+            }
+        }
+
+        for (_, symbols) in &symbol_tables {
+            for symbol in symbols {
+                if symbol::is_marker(&symbol.name)
+                    && !references.iter().any(|r| r.symbol == symbol.name) {
+                    references.push(Reference {
+                        member: None,
+                        symbol: symbol.name.clone(),
+                        function: None,
+                        section: None,
+                        address: 0,
+                        frames: Vec::new(),
+                    });
+                }
+            }
+        }
+        Ok(references)
+    }
+}
+
+impl<'a> Elf<'a> {
+    /// The debug sections, with relocations applied for relocatable objects.
+    fn dwarf(&self, symbol_tables: &[(usize, Vec<Symbol>)]) -> io::Result<Dwarf<'a>> {
+        let mut sections = Vec::new();
+        for name in crate::dwarf::SECTIONS {
+            sections.push(self.debug_section(name, symbol_tables)?);
+        }
+        let mut sections = sections.into_iter();
+        let mut next = || sections.next().unwrap_or_default();
+        Ok(Dwarf {
+            little_endian: self.is_le,
+            info: next(),
+            abbrev: next(),
+            str: next(),
+            line_str: next(),
+            line: next(),
+            ranges: next(),
+            rnglists: next(),
+            addr: next(),
+            str_offsets: next(),
+        })
+    }
+
+    fn debug_section(&self, name: &str, symbol_tables: &[(usize, Vec<Symbol>)]) -> io::Result<DebugSection<'a>> {
+        let index = match self.sections.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => return Ok(DebugSection::default()),
+        };
+        let data = self.section_data(&self.sections[index])?;
+        let mut section = DebugSection { data: data.into(), targets: HashMap::new() };
+        // Linked files keep relocations already applied with `--emit-relocs`
+        if !self.relocatable {
+            return Ok(section);
+        }
+        for relocations in &self.sections {
+            if (relocations.kind != SHT_RELA && relocations.kind != SHT_REL) || relocations.info as usize != index {
+                continue;
+            }
+            let symbols = match symbol_tables.iter().find(|(index, _)| *index == relocations.link as usize) {
+                Some((_, symbols)) => symbols,
+                None => continue,
+            };
+            for relocation in self.relocations(relocations)? {
+                let (size, symbol) = match (self.relocation_size(relocation.kind), symbols.get(relocation.symbol)) {
+                    (Some(size), Some(symbol)) => (size, symbol),
+                    _ => continue,
+                };
+                let offset = relocation.offset as usize;
+                let bytes = match section.data.to_mut().get_mut(offset..offset.saturating_add(size)) {
+                    Some(bytes) => bytes,
+                    None => continue,
+                };
+                let addend = match relocations.kind {
+                    SHT_RELA => relocation.addend,
+                    _ if self.is_le => bytes.iter().rev().fold(0, |v, &b| v << 8 | b as i64),
+                    _ => bytes.iter().fold(0, |v, &b| v << 8 | b as i64),
+                };
+                let value = symbol.value.wrapping_add(addend as u64);
+                match self.is_le {
+                    true => bytes.copy_from_slice(&value.to_le_bytes()[..size]),
+                    false => bytes.copy_from_slice(&value.to_be_bytes()[8 - size..]),
+                }
+                if self.sections.get(symbol.section as usize).is_some_and(|s| s.flags & SHF_ALLOC != 0) {
+                    section.targets.insert(offset, symbol.section);
+                }
+            }
+        }
+        Ok(section)
+    }
+
+    /// The size of the absolute relocations used in debug sections.
+    fn relocation_size(&self, kind: u32) -> Option<usize> {
+        match (self.machine, kind) {
+            // x86-64: R_X86_64_64, R_X86_64_32, R_X86_64_32S
+            (62, 1) => Some(8),
+            (62, 10) | (62, 11) => Some(4),
+            // i386 and ARM: R_386_32, R_ARM_ABS32
+            (3, 1) | (40, 2) => Some(4),
+            // AArch64: R_AARCH64_ABS64, R_AARCH64_ABS32
+            (183, 257) => Some(8),
+            (183, 258) => Some(4),
+            // RISC-V: R_RISCV_32, R_RISCV_64
+            (243, 1) => Some(4),
+            (243, 2) => Some(8),
+            _ => None,
+        }
+    }
+}
+
+/// The NUL terminated string at `offset` in a string table.
+fn string(table: &[u8], offset: usize) -> String {
+    let s = table.get(offset..).unwrap_or(&[]);
+    let end = s.iter().position(|&b| b == 0).unwrap_or(s.len());
+    String::from_utf8_lossy(&s[..end]).into_owned()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub const MAIN: &str = "_ZN3app4main17h80d95ca387d501e6E";
+    pub const MARKER: &str = "___unreachable_static___@app@src/main.rs:3:5";
+
+    /// A relocatable x86-64 object with `app::main` in `.text.<main>`, calling
+    /// the marker at offset 5.
+    pub fn object() -> Vec<u8> {
+        let text_name = format!(".text.{}", MAIN);
+        let shstrtab = format!("\0{}\0.rela{}\0.symtab\0.strtab\0.shstrtab\0", text_name, text_name);
+        let name = |n: &str| shstrtab.find(&format!("\0{}\0", n)).unwrap() as u32 + 1;
+        let strtab = format!("\0{}\0{}\0", MAIN, MARKER);
+
+        let text = [0x50, 0x31, 0xc0, 0x90, 0xe8, 0, 0, 0, 0, 0x0f, 0x0b, 0, 0, 0, 0, 0];
+        let mut rela = Vec::new();
+        rela.extend(5u64.to_le_bytes());
+        rela.extend((2u64 << 32 | 4).to_le_bytes());
+        rela.extend((-4i64).to_le_bytes());
+        let mut symtab = vec![0; 24];
+        for (name, info, shndx, size) in [(1u32, 0x12u8, 1u16, 16u64), (2 + MAIN.len() as u32, 0x10, 0, 0)] {
+            symtab.extend(name.to_le_bytes());
+            symtab.extend([info, 0]);
+            symtab.extend(shndx.to_le_bytes());
+            symtab.extend(0u64.to_le_bytes());
+            symtab.extend(size.to_le_bytes());
+        }
+
+        let mut elf = vec![0; 64];
+        let mut headers = vec![0; 64];
+        let sections = [
+            (name(&text_name), 1, 6u64, &text[..], 0u32, 0u32, 0u64),
+            (name(&format!(".rela{}", text_name)), SHT_RELA, 0x40, &rela[..], 3, 1, 24),
+            (name(".symtab"), SHT_SYMTAB, 0, &symtab[..], 4, 2, 24),
+            (name(".strtab"), 3, 0, strtab.as_bytes(), 0, 0, 0),
+            (name(".shstrtab"), 3, 0, shstrtab.as_bytes(), 0, 0, 0),
+        ];
+        for (name, kind, flags, data, link, info, entsize) in sections {
+            headers.extend(name.to_le_bytes());
+            headers.extend(kind.to_le_bytes());
+            headers.extend(flags.to_le_bytes());
+            headers.extend(0u64.to_le_bytes());
+            headers.extend((elf.len() as u64).to_le_bytes());
+            headers.extend((data.len() as u64).to_le_bytes());
+            headers.extend(link.to_le_bytes());
+            headers.extend(info.to_le_bytes());
+            headers.extend(1u64.to_le_bytes());
+            headers.extend(entsize.to_le_bytes());
+            elf.extend(data);
+        }
+        let shoff = elf.len() as u64;
+        elf.extend(headers);
+
+        elf[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\0");
+        elf[16..18].copy_from_slice(&ET_REL.to_le_bytes());
+        elf[18..20].copy_from_slice(&62u16.to_le_bytes());
+        elf[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        elf[0x34..0x36].copy_from_slice(&64u16.to_le_bytes());
+        elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        elf[0x3c..0x3e].copy_from_slice(&6u16.to_le_bytes());
+        elf[0x3e..0x40].copy_from_slice(&5u16.to_le_bytes());
+        elf
+    }
+
+    fn member(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut member = format!("{:16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, 0, 0, 644, data.len()).into_bytes();
+        member.extend(data);
+        if data.len() % 2 == 1 {
+            member.push(b'\n');
+        }
+        member
+    }
+
+    #[test]
+    fn relocatable() {
+        let references = scan(&object()).unwrap();
+        assert_eq!(references, [Reference {
+            member: None,
+            symbol: MARKER.into(),
+            function: Some("app::main".into()),
+            section: Some(format!(".text.{}", MAIN)),
+            address: 5,
+            frames: Vec::new(),
+        }]);
+        assert_eq!(diagnostics(&references)[0].functions, ["app::main"]);
+    }
+
+    #[test]
+    fn rlib() {
+        let long_name = "app-0123456789abcdef.app.1a2b3c4d-cgu.0.rcgu.o/\n";
+        let mut archive = b"!<arch>\n".to_vec();
+        archive.extend(member("/", &[0; 4]));
+        archive.extend(member("//", long_name.as_bytes()));
+        archive.extend(member("lib.rmeta/", b"rust"));
+        archive.extend(member("/0", &object()));
+        archive.extend(member("#1/5", b"b.o\0\0\x7fEL"));
+
+        let references = scan(&archive).unwrap();
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].member.as_deref(), Some("app-0123456789abcdef.app.1a2b3c4d-cgu.0.rcgu.o"));
+        assert_eq!(references[0].function.as_deref(), Some("app::main"));
+
+        assert!(scan(b"#!/bin/sh\n").is_err());
+        assert!(scan(&object()[..100]).is_err());
+    }
+}
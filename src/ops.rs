@@ -0,0 +1,284 @@
+//! Operand types for `checked_ops!()` and `unchecked_ops!()`.
+//!
+//! The macros wrap every operand of the expression in one of these types and
+//! leave the operators in place, so the expression keeps Rust's precedence
+//! while each operator is implemented by its checked or unchecked counterpart.
+
+use core::{hint, ops};
+
+/// An operand of `checked_ops!()`, or `None` once any operation overflowed.
+///
+/// Overflow is carried through the rest of the expression and only checked
+/// once at the end, so each `checked_ops!()` is a single site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checked<T>(pub Option<T>);
+
+impl<T> Checked<T> {
+    #[inline(always)]
+    pub fn new(v: T) -> Self {
+        Checked(Some(v))
+    }
+}
+
+/// An operand of `unchecked_ops!()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unchecked<T>(T);
+
+impl<T> Unchecked<T> {
+    /// # Safety
+    ///
+    /// No operation performed on the value may overflow, divide by zero, or
+    /// shift out of range.
+    #[inline(always)]
+    pub unsafe fn new(v: T) -> Self {
+        Unchecked(v)
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// Integers supported by `checked_ops!()` and `unchecked_ops!()`.
+pub trait Int: Copy {
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+    fn checked_neg(self) -> Option<Self>;
+    fn checked_shl(self, rhs: u32) -> Option<Self>;
+    fn checked_shr(self, rhs: u32) -> Option<Self>;
+
+    /// # Safety
+    ///
+    /// `self + rhs` must not overflow.
+    unsafe fn unchecked_add(self, rhs: Self) -> Self;
+    /// # Safety
+    ///
+    /// `self - rhs` must not overflow.
+    unsafe fn unchecked_sub(self, rhs: Self) -> Self;
+    /// # Safety
+    ///
+    /// `self * rhs` must not overflow.
+    unsafe fn unchecked_mul(self, rhs: Self) -> Self;
+}
+
+/// Signed integers, the only ones `unchecked_ops!()` can negate.
+pub trait Signed: Int {
+    /// # Safety
+    ///
+    /// `self` must not be the minimum value.
+    unsafe fn unchecked_neg(self) -> Self;
+}
+
+macro_rules! impl_int {
+    ($($ty:ty)*) => {$(
+        impl Int for $ty {
+            #[inline(always)]
+            fn checked_add(self, rhs: Self) -> Option<Self> { <$ty>::checked_add(self, rhs) }
+            #[inline(always)]
+            fn checked_sub(self, rhs: Self) -> Option<Self> { <$ty>::checked_sub(self, rhs) }
+            #[inline(always)]
+            fn checked_mul(self, rhs: Self) -> Option<Self> { <$ty>::checked_mul(self, rhs) }
+            #[inline(always)]
+            fn checked_div(self, rhs: Self) -> Option<Self> { <$ty>::checked_div(self, rhs) }
+            #[inline(always)]
+            fn checked_rem(self, rhs: Self) -> Option<Self> { <$ty>::checked_rem(self, rhs) }
+            #[inline(always)]
+            fn checked_neg(self) -> Option<Self> { <$ty>::checked_neg(self) }
+            #[inline(always)]
+            fn checked_shl(self, rhs: u32) -> Option<Self> { <$ty>::checked_shl(self, rhs) }
+            #[inline(always)]
+            fn checked_shr(self, rhs: u32) -> Option<Self> { <$ty>::checked_shr(self, rhs) }
+            #[inline(always)]
+            unsafe fn unchecked_add(self, rhs: Self) -> Self { <$ty>::unchecked_add(self, rhs) }
+            #[inline(always)]
+            unsafe fn unchecked_sub(self, rhs: Self) -> Self { <$ty>::unchecked_sub(self, rhs) }
+            #[inline(always)]
+            unsafe fn unchecked_mul(self, rhs: Self) -> Self { <$ty>::unchecked_mul(self, rhs) }
+        }
+    )*};
+}
+
+impl_int! { i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize }
+
+macro_rules! impl_signed {
+    ($($ty:ty)*) => {$(
+        impl Signed for $ty {
+            #[inline(always)]
+            unsafe fn unchecked_neg(self) -> Self { <$ty>::unchecked_sub(0, self) }
+        }
+    )*};
+}
+
+impl_signed! { i8 i16 i32 i64 i128 isize }
+
+macro_rules! impl_checked {
+    ($($trait:ident::$fn:ident($rhs:ty) => $op:expr;)*) => {$(
+        impl<T: Int> ops::$trait<Checked<$rhs>> for Checked<T> {
+            type Output = Self;
+
+            #[inline(always)]
+            fn $fn(self, rhs: Checked<$rhs>) -> Self {
+                Checked(match (self.0, rhs.0) {
+                    (Some(lhs), Some(rhs)) => $op(lhs, rhs),
+                    _ => None,
+                })
+            }
+        }
+    )*};
+}
+
+impl_checked! {
+    Add::add(T) => T::checked_add;
+    Sub::sub(T) => T::checked_sub;
+    Mul::mul(T) => T::checked_mul;
+    Div::div(T) => T::checked_div;
+    Rem::rem(T) => T::checked_rem;
+    Shl::shl(u32) => T::checked_shl;
+    Shr::shr(u32) => T::checked_shr;
+}
+
+macro_rules! impl_bitwise {
+    ($($trait:ident::$fn:ident;)*) => {$(
+        impl<T: Int + ops::$trait<Output = T>> ops::$trait for Checked<T> {
+            type Output = Self;
+
+            #[inline(always)]
+            fn $fn(self, rhs: Self) -> Self {
+                Checked(match (self.0, rhs.0) {
+                    (Some(lhs), Some(rhs)) => Some(ops::$trait::$fn(lhs, rhs)),
+                    _ => None,
+                })
+            }
+        }
+    )*};
+}
+
+impl_bitwise! {
+    BitAnd::bitand;
+    BitOr::bitor;
+    BitXor::bitxor;
+}
+
+impl<T: Signed> ops::Neg for Checked<T> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Checked(self.0.and_then(T::checked_neg))
+    }
+}
+
+macro_rules! impl_unchecked {
+    ($($trait:ident::$fn:ident($rhs:ty) => $op:expr;)*) => {$(
+        impl<T: Int> ops::$trait<Unchecked<$rhs>> for Unchecked<T> {
+            type Output = Self;
+
+            #[inline(always)]
+            fn $fn(self, rhs: Unchecked<$rhs>) -> Self {
+                // Safety: promised by `Unchecked::new`
+                unsafe { Unchecked($op(self.0, rhs.0)) }
+            }
+        }
+    )*};
+}
+
+impl_unchecked! {
+    Add::add(T) => T::unchecked_add;
+    Sub::sub(T) => T::unchecked_sub;
+    Mul::mul(T) => T::unchecked_mul;
+    Div::div(T) => |lhs: T, rhs| match lhs.checked_div(rhs) {
+        Some(v) => v,
+        None => hint::unreachable_unchecked(),
+    };
+    Rem::rem(T) => |lhs: T, rhs| match lhs.checked_rem(rhs) {
+        Some(v) => v,
+        None => hint::unreachable_unchecked(),
+    };
+    Shl::shl(u32) => |lhs: T, rhs| match lhs.checked_shl(rhs) {
+        Some(v) => v,
+        None => hint::unreachable_unchecked(),
+    };
+    Shr::shr(u32) => |lhs: T, rhs| match lhs.checked_shr(rhs) {
+        Some(v) => v,
+        None => hint::unreachable_unchecked(),
+    };
+}
+
+macro_rules! impl_unchecked_bitwise {
+    ($($trait:ident::$fn:ident;)*) => {$(
+        impl<T: Int + ops::$trait<Output = T>> ops::$trait for Unchecked<T> {
+            type Output = Self;
+
+            #[inline(always)]
+            fn $fn(self, rhs: Self) -> Self {
+                Unchecked(ops::$trait::$fn(self.0, rhs.0))
+            }
+        }
+    )*};
+}
+
+impl_unchecked_bitwise! {
+    BitAnd::bitand;
+    BitOr::bitor;
+    BitXor::bitxor;
+}
+
+impl<T: Signed> ops::Neg for Unchecked<T> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        // Safety: promised by `Unchecked::new`
+        unsafe { Unchecked(self.0.unchecked_neg()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+    fn precedence() {
+        let (a, b, c) = (3u8, 4u8, 5u8);
+        assert_eq!(checked_ops!(a * b + c), 17);
+        assert_eq!(checked_ops!(a + b * c), 23);
+        assert_eq!(checked_ops!((a + b) * c), 35);
+        assert_eq!(checked_ops!(c - b - 1 + a), 3);
+        assert_eq!(checked_ops!(c % b << 3 | 1), 9);
+        assert_eq!(checked_ops!(-(a as i32) * -2), 6);
+        assert_eq!(checked_ops!([a, b][1] * u8::max(b, c) / 2), 10);
+        assert_eq!(checked_ops!((a as u64) << 40 >> 38), 12);
+    }
+
+    #[test]
+    fn unchecked() {
+        let (a, b, c) = (3u8, 4u8, 5u8);
+        unsafe {
+            assert_eq!(unchecked_ops!(a * b + c), 17);
+            assert_eq!(unchecked_ops!((a + b) * c - b / 2 % 3), 33);
+            assert_eq!(unchecked_ops!(c % b << 3 | 1), 9);
+            assert_eq!(unchecked_ops!(-(a as i32) * -2 >> 1), 3);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "arithmetic overflow: a - b")]
+    #[cfg(any(reachability_unchecked = "checked", all(debug_assertions, not(reachability_unchecked = "unchecked"))))]
+    fn unchecked_overflow() {
+        let (a, b) = (3u8, 4u8);
+        unsafe {
+            unchecked_ops!(a - b);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "arithmetic overflow: a * b + c")]
+    #[cfg(any(not(feature = "static"), all(debug_assertions, not(feature = "always-static"))))]
+    fn overflow() {
+        let (a, b, c) = (100u8, 2u8, 56u8);
+        checked_ops!(a * b + c);
+    }
+}
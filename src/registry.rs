@@ -0,0 +1,69 @@
+//! Site registry for the `warn` feature.
+//!
+//! With `warn`, `unreachable_static!()` sites don't reference an undefined
+//! symbol. Instead each stores its link name (see [`crate::symbol`]), NUL
+//! terminated, in the [`SECTION`] link section (`__DATA,__reach_sites` on
+//! Apple targets) and calls a fallback that panics when reached. Only the
+//! site's own code refers to its descriptor, so the descriptor is discarded
+//! whenever the site is, and the section of a linked binary lists exactly
+//! the sites that survived. The binary links either way, and
+//! `cargo reachability verify` reports every surviving site at once.
+
+use core::str;
+use crate::symbol;
+
+/// The name of the link section holding site descriptors on ELF targets.
+pub const SECTION: &str = "reachability_sites";
+
+/// The descriptor of a site with link name `symbol`, which must be NUL
+/// terminated and `N` bytes long.
+#[doc(hidden)]
+pub const fn descriptor<const N: usize>(symbol: &str) -> [u8; N] {
+    let symbol = symbol.as_bytes();
+    let mut descriptor = [0; N];
+    let mut i = 0;
+    while i < N {
+        descriptor[i] = symbol[i];
+        i += 1;
+    }
+    descriptor
+}
+
+/// The fallback called by registered sites.
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn reached(descriptor: &'static [u8]) -> ! {
+    crate::internal_unreachable_static_panic(str::from_utf8(descriptor).unwrap_or("").trim_end_matches('\0'))
+}
+
+/// The link names of the sites registered in the contents of a
+/// [`SECTION`].
+pub fn symbols(section: &[u8]) -> impl Iterator<Item = &str> {
+    section.split(|&b| b == 0)
+        .filter_map(|descriptor| str::from_utf8(descriptor).ok())
+        .filter(|symbol| symbol::is_marker(symbol))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptors() {
+        const SYMBOL: &str = "___unreachable_static___@app@src/main.rs:3:5: a\0";
+        static SITE: [u8; SYMBOL.len()] = descriptor(SYMBOL);
+        let mut section = SITE.to_vec();
+        section.extend(b"___unreachable_static___@app@src/lib.rs:7:9\0\0\0");
+
+        let sites: Vec<_> = symbols(&section).filter_map(symbol::decode).map(|site| site.to_string()).collect();
+        assert_eq!(sites, ["src/main.rs:3:5: a", "src/lib.rs:7:9"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "internal error: entered unreachable code: a")]
+    fn reach() {
+        reached(b"___unreachable_static___@app@src/main.rs:3:5: a\0");
+    }
+}
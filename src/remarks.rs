@@ -0,0 +1,173 @@
+//! LLVM optimization remarks for `cargo reachability explain`.
+//!
+//! Built with `-C remark=all -C debuginfo=line-tables-only`, rustc reports
+//! every optimization LLVM made or missed as a note:
+//!
+//! ```text
+//! src/main.rs:4:23 inline (missed): ___unreachable_static___@app@src/main.rs:4:23 will not be inlined into _ZN3app4main17h0123456789abcdefE because its definition is unavailable
+//! ```
+//!
+//! Each surviving site's marker shows up as a call that couldn't be inlined,
+//! naming the function holding it, and the inliner's remarks show where that
+//! function was inlined in turn. [`explain`] keeps the missed optimizations
+//! in those functions: calls that weren't inlined into them, their own calls
+//! that weren't inlined, and other passes' misses in the site's file.
+
+use std::fmt;
+use crate::demangle::demangle;
+use crate::symbol;
+
+/// Whether LLVM applied the optimization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Passed,
+    Missed,
+    Analysis,
+}
+
+/// A remark rustc printed for `-C remark`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Remark {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    /// The LLVM pass, e.g. `inline` or `licm`.
+    pub pass: String,
+    pub kind: Kind,
+    pub message: String,
+}
+
+impl Remark {
+    /// Parse a remark from a rustc note.
+    pub fn parse(note: &str) -> Option<Remark> {
+        let (kind, i, len) = [(Kind::Passed, " (success): "), (Kind::Missed, " (missed): "), (Kind::Analysis, " (analysis): ")]
+            .iter()
+            .filter_map(|&(kind, pattern)| note.find(pattern).map(|i| (kind, i, pattern.len())))
+            .min_by_key(|&(_, i, _)| i)?;
+        let (location, pass) = note[..i].rsplit_once(' ')?;
+        let mut location = location.rsplitn(3, ':');
+        let column = location.next()?.parse().ok()?;
+        let line = location.next()?.parse().ok()?;
+        let file = location.next()?;
+        Some(Remark { file: file.into(), line, column, pass: pass.into(), kind, message: note[i + len..].into() })
+    }
+
+    /// The callee and caller of an inliner remark, demangled.
+    pub fn inlining(&self) -> Option<(String, String)> {
+        if self.pass != "inline" {
+            return None;
+        }
+        let i = self.message.find(" inlined into ")?;
+        let callee = self.message[..i].trim_end_matches(" will not be").trim_end_matches(" not").trim_matches('\'');
+        let caller = self.message[i + 14..].split(' ').next()?.trim_matches('\'');
+        Some((demangle(callee).into_owned(), demangle(caller).into_owned()))
+    }
+}
+
+impl fmt::Display for Remark {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{} {}: ", self.file, self.line, self.column, self.pass)?;
+        // Demangle the functions named in the message
+        for (i, word) in self.message.split(' ').enumerate() {
+            let quoted = word.trim_matches('\'');
+            let name = demangle(quoted);
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match name.len() < quoted.len() {
+                true => write!(f, "`{}`", name)?,
+                false => write!(f, "{}", word)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Passes after optimization, whose remarks don't affect elimination.
+const CODEGEN_PASSES: [&str; 6] = ["asm-printer", "prologepilog", "regalloc", "sdagisel", "size-info", "stack-frame-layout"];
+
+/// Why a site survived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation<'a> {
+    /// The functions the site's marker was found in, demangled.
+    pub functions: Vec<String>,
+    /// The missed optimizations in them, in source order.
+    pub remarks: Vec<&'a Remark>,
+}
+
+/// Explain the site with link name `symbol` from `remarks`, starting from
+/// the demangled `functions` the linker found referencing it, if any.
+pub fn explain<'a>(remarks: &'a [Remark], symbol: &str, functions: &[String]) -> Explanation<'a> {
+    let site = symbol::decode(symbol);
+    let inlinings: Vec<_> = remarks.iter().filter_map(|r| r.inlining().map(|i| (r, i))).collect();
+
+    let mut holders = functions.to_vec();
+    for (_, (callee, caller)) in &inlinings {
+        if callee == symbol && !holders.contains(caller) {
+            holders.push(caller.clone());
+        }
+    }
+    // Functions the holders were inlined into hold the marker too
+    let mut i = 0;
+    while i < holders.len() {
+        for (remark, (callee, caller)) in &inlinings {
+            if remark.kind == Kind::Passed && *callee == holders[i] && !holders.contains(caller) {
+                holders.push(caller.clone());
+            }
+        }
+        i += 1;
+    }
+
+    let mut relevant: Vec<&Remark> = remarks.iter()
+        .filter(|r| r.kind == Kind::Missed && !CODEGEN_PASSES.contains(&&r.pass[..]))
+        .filter(|r| match r.inlining() {
+            Some((callee, caller)) => !symbol::is_marker(&callee) && (holders.contains(&caller) || holders.contains(&callee)),
+            None => site.is_some_and(|site| r.file == site.file || r.file.ends_with(&format!("/{}", site.file))),
+        })
+        .collect();
+    relevant.sort_by_key(|r| (&r.file, r.line, r.column));
+    relevant.dedup();
+    Explanation { functions: holders, remarks: relevant }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKER: &str = "___unreachable_static___@app@src/main.rs:4:23: seven";
+
+    fn remarks() -> Vec<Remark> {
+        [
+            "/t/src/main.rs:4:23 inline (missed): ___unreachable_static___@app@src/main.rs:4:23: seven will not be inlined into _ZN3app5check17h0123456789abcdefE because its definition is unavailable",
+            "/t/src/main.rs:9:5 inline (success): '_ZN3app5check17h0123456789abcdefE' inlined into '_ZN3app4main17h0123456789abcdefE' with (cost=15, threshold=45) at callsite _ZN3app4main17h0123456789abcdefE:4:5;",
+            "/t/src/main.rs:3:5 inline (missed): '_ZN3app5parse17h0123456789abcdefE' not inlined into '_ZN3app5check17h0123456789abcdefE' because too costly to inline (cost=400, threshold=250)",
+            "/t/src/main.rs:3:5 inline (missed): '_ZN3app5parse17h0123456789abcdefE' not inlined into '_ZN3app5check17h0123456789abcdefE' because too costly to inline (cost=400, threshold=250)",
+            "/t/src/main.rs:2:9 licm (missed): failed to move load with loop-invariant address because the loop may invalidate its value",
+            "/t/src/other.rs:2:9 licm (missed): failed to move load with loop-invariant address because the loop may invalidate its value",
+            "/t/src/other.rs:7:1 inline (missed): '_ZN3app5other17h0123456789abcdefE' not inlined into '_ZN3app3run17h0123456789abcdefE' because too costly to inline (cost=400, threshold=250)",
+            "/t/src/main.rs:1:0 prologepilog (analysis): 184 stack bytes in function '_ZN3app4main17h0123456789abcdefE'",
+            "/t/src/main.rs:1:1 regalloc (missed): 22 virtual registers copies 4.024842e+01 total copies cost generated in function",
+            "/t/src/main.rs:5:20 inline (missed): ___unreachable_static___@app@src/main.rs:5:20 will not be inlined into _ZN3app4main17h0123456789abcdefE because its definition is unavailable",
+        ].iter().map(|note| Remark::parse(note).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let remarks = remarks();
+        assert_eq!(remarks[0].file, "/t/src/main.rs");
+        assert_eq!((remarks[0].line, remarks[0].column, remarks[0].kind), (4, 23, Kind::Missed));
+        assert_eq!(remarks[0].inlining(), Some((MARKER.into(), "app::check".into())));
+        assert_eq!(remarks[1].inlining(), Some(("app::check".into(), "app::main".into())));
+        assert_eq!(remarks[2].to_string(), "/t/src/main.rs:3:5 inline: `app::parse` not inlined into `app::check` because too costly to inline (cost=400, threshold=250)");
+        assert_eq!(Remark::parse("<unknown>:0:0 sdagisel (missed): FastISel missed").unwrap().file, "<unknown>");
+        assert_eq!(Remark::parse("warning: unused variable"), None);
+    }
+
+    #[test]
+    fn explanation() {
+        let remarks = remarks();
+        let explanation = explain(&remarks, MARKER, &[]);
+        assert_eq!(explanation.functions, ["app::check", "app::main"]);
+        assert_eq!(explanation.remarks, [&remarks[4], &remarks[2]]);
+    }
+}
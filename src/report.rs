@@ -0,0 +1,355 @@
+//! Machine-readable reports of the `cargo reachability` commands.
+//!
+//! With `--format json` or `--format sarif`, a command prints its findings
+//! as one [`Report`] on stdout instead of text. The JSON is versioned by
+//! [`VERSION`], which changes whenever a field is removed or changes meaning:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "command": "matrix",
+//!   "findings": [
+//!     {
+//!       "rule": "unproven-site",
+//!       "level": "error",
+//!       "message": "`unreachable_static!()` was not eliminated: bad header",
+//!       "location": { "file": "src/net.rs", "line": 88, "column": 13 },
+//!       "properties": { "crate": "app", "configuration": "opt-level=3 lto=fat", "target": "app" }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `location` is `null` for findings without one, and its `line` and
+//! `column` are left out when unknown. SARIF 2.1.0 output carries the same
+//! findings as results, for code scanning tools to annotate the lines.
+
+use std::fmt;
+use crate::json::Value;
+use crate::symbol;
+
+/// The version of the JSON report format.
+pub const VERSION: u32 = 1;
+
+/// The output format of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "sarif" => Some(Format::Sarif),
+            _ => None,
+        }
+    }
+}
+
+/// What a finding reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// An `unreachable_static!()` site survived optimization.
+    UnprovenSite,
+    /// A target expected to fail to link linked.
+    UnexpectedLink,
+    /// A target failed to build before linking.
+    BuildError,
+    /// A site or configuration is missing from the baseline.
+    BaselineRegression,
+    /// An unchecked use has no justification comment.
+    UnjustifiedUnchecked,
+    /// A use of a static macro or method.
+    StaticUse,
+    /// A use of an unchecked macro or method.
+    UncheckedUse,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::UnprovenSite,
+        Rule::UnexpectedLink,
+        Rule::BuildError,
+        Rule::BaselineRegression,
+        Rule::UnjustifiedUnchecked,
+        Rule::StaticUse,
+        Rule::UncheckedUse,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::UnprovenSite => "unproven-site",
+            Rule::UnexpectedLink => "unexpected-link",
+            Rule::BuildError => "build-error",
+            Rule::BaselineRegression => "baseline-regression",
+            Rule::UnjustifiedUnchecked => "unjustified-unchecked",
+            Rule::StaticUse => "static-use",
+            Rule::UncheckedUse => "unchecked-use",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::UnprovenSite => "An `unreachable_static!()` site was not eliminated by the optimizer",
+            Rule::UnexpectedLink => "A target expected to fail to link was linked",
+            Rule::BuildError => "A target failed to build",
+            Rule::BaselineRegression => "A site that isn't proven is missing from the baseline",
+            Rule::UnjustifiedUnchecked => "An unchecked use has no justification comment",
+            Rule::StaticUse => "A use relying on a static proof",
+            Rule::UncheckedUse => "A use that is undefined behaviour in release builds if reached",
+        }
+    }
+
+    /// The SARIF level: `error`, `warning` or `note`.
+    pub fn level(self) -> &'static str {
+        match self {
+            Rule::UnprovenSite | Rule::UnexpectedLink | Rule::BuildError | Rule::BaselineRegression => "error",
+            Rule::UnjustifiedUnchecked => "warning",
+            Rule::StaticUse | Rule::UncheckedUse => "note",
+        }
+    }
+}
+
+/// A source location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl Location {
+    pub fn new(file: &str, line: u32, column: u32) -> Self {
+        Location { file: file.into(), line: Some(line), column: Some(column) }
+    }
+
+    /// Parse `<file>`, `<file>:<line>` or `<file>:<line>:<column>`.
+    pub fn parse(s: &str) -> Self {
+        let mut parts = s.rsplitn(3, ':');
+        let numbers: Vec<u32> = parts.by_ref().take(2).map_while(|n| n.parse().ok()).collect();
+        match numbers[..] {
+            [column, line] => Location { file: s.rsplitn(3, ':').nth(2).unwrap_or(s).into(), line: Some(line), column: Some(column) },
+            [line, ..] => Location { file: s.rsplit_once(':').map_or(s, |(file, _)| file).into(), line: Some(line), column: None },
+            [] => Location { file: s.into(), line: None, column: None },
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut entries = vec![("file".into(), Value::String(self.file.clone()))];
+        entries.extend(self.line.map(|line| ("line".into(), Value::Number(line.into()))));
+        entries.extend(self.column.map(|column| ("column".into(), Value::Number(column.into()))));
+        Value::Object(entries)
+    }
+
+    /// The SARIF artifact URI: relative paths as they are, absolute ones as
+    /// `file` URIs.
+    fn uri(&self) -> String {
+        let path = self.file.replace('\\', "/");
+        match path.starts_with('/') {
+            true => format!("file://{}", path),
+            false if path.as_bytes().get(1) == Some(&b':') => format!("file:///{}", path),
+            false => path,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// One result of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub message: String,
+    pub location: Option<Location>,
+    /// Context such as the configuration or target, in order.
+    pub properties: Vec<(String, String)>,
+}
+
+impl Finding {
+    pub fn new(rule: Rule, message: impl Into<String>) -> Self {
+        Finding { rule, message: message.into(), location: None, properties: Vec::new() }
+    }
+
+    /// A finding at the site with link name `symbol`.
+    pub fn site(rule: Rule, symbol: &str) -> Self {
+        match symbol::decode(symbol) {
+            Some(site) => {
+                let message = match site.message {
+                    Some(message) => format!("`unreachable_static!()` was not eliminated: {}", message),
+                    None => "`unreachable_static!()` was not eliminated".into(),
+                };
+                Finding::new(rule, message)
+                    .at(Location::new(site.file, site.line, site.column))
+                    .with("crate", site.crate_name())
+            },
+            None => Finding::new(rule, format!("`unreachable_static!()` was not eliminated: {}", symbol)),
+        }
+    }
+
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn with(mut self, key: &str, value: impl fmt::Display) -> Self {
+        self.properties.push((key.into(), value.to_string()));
+        self
+    }
+
+    fn properties(&self) -> Value {
+        Value::Object(self.properties.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect())
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The findings of one command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The `cargo reachability` subcommand.
+    pub command: String,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    pub fn new(command: &str) -> Self {
+        Report { command: command.into(), findings: Vec::new() }
+    }
+
+    /// The versioned JSON report.
+    pub fn to_json(&self) -> Value {
+        let findings = self.findings.iter().map(|finding| Value::Object(vec![
+            ("rule".into(), Value::String(finding.rule.id().into())),
+            ("level".into(), Value::String(finding.rule.level().into())),
+            ("message".into(), Value::String(finding.message.clone())),
+            ("location".into(), finding.location.as_ref().map_or(Value::Null, Location::to_json)),
+            ("properties".into(), finding.properties()),
+        ])).collect();
+        Value::Object(vec![
+            ("version".into(), Value::Number(VERSION.into())),
+            ("command".into(), Value::String(self.command.clone())),
+            ("findings".into(), Value::Array(findings)),
+        ])
+    }
+
+    /// A SARIF 2.1.0 log with one run.
+    pub fn to_sarif(&self) -> Value {
+        let text = |s: &str| Value::Object(vec![("text".into(), Value::String(s.into()))]);
+        let rules = Rule::ALL.iter().map(|rule| Value::Object(vec![
+            ("id".into(), Value::String(rule.id().into())),
+            ("shortDescription".into(), text(rule.description())),
+            ("defaultConfiguration".into(), Value::Object(vec![("level".into(), Value::String(rule.level().into()))])),
+        ])).collect();
+
+        let results = self.findings.iter().map(|finding| {
+            let mut result = vec![
+                ("ruleId".into(), Value::String(finding.rule.id().into())),
+                ("ruleIndex".into(), Value::Number(Rule::ALL.iter().position(|&r| r == finding.rule).unwrap_or(0) as f64)),
+                ("level".into(), Value::String(finding.rule.level().into())),
+                ("message".into(), text(&finding.message)),
+            ];
+            if let Some(location) = &finding.location {
+                let mut region = Vec::new();
+                region.extend(location.line.map(|line| ("startLine".into(), Value::Number(line.into()))));
+                region.extend(location.column.map(|column| ("startColumn".into(), Value::Number(column.into()))));
+                let mut physical = vec![
+                    ("artifactLocation".into(), Value::Object(vec![("uri".into(), Value::String(location.uri()))])),
+                ];
+                if !region.is_empty() {
+                    physical.push(("region".into(), Value::Object(region)));
+                }
+                result.push(("locations".into(), Value::Array(vec![
+                    Value::Object(vec![("physicalLocation".into(), Value::Object(physical))]),
+                ])));
+            }
+            if !finding.properties.is_empty() {
+                result.push(("properties".into(), finding.properties()));
+            }
+            Value::Object(result)
+        }).collect();
+
+        let driver = Value::Object(vec![
+            ("name".into(), Value::String("cargo-reachability".into())),
+            ("rules".into(), Value::Array(rules)),
+        ]);
+        Value::Object(vec![
+            ("$schema".into(), Value::String("https://json.schemastore.org/sarif-2.1.0.json".into())),
+            ("version".into(), Value::String("2.1.0".into())),
+            ("runs".into(), Value::Array(vec![Value::Object(vec![
+                ("tool".into(), Value::Object(vec![("driver".into(), driver)])),
+                ("results".into(), Value::Array(results)),
+            ])])),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        let mut report = Report::new("matrix");
+        report.findings.push(Finding::site(Rule::UnprovenSite, "___unreachable_static___@app::net@src/net.rs:88:13: bad header")
+            .with("configuration", "opt-level=3 lto=fat")
+            .with("target", "app"));
+        report.findings.push(Finding::new(Rule::UnexpectedLink, "`fail` linked but was expected to fail")
+            .with("configuration", "opt-level=3 lto=fat")
+            .with("target", "fail"));
+        report.findings.push(Finding::new(Rule::BaselineRegression, "app src/lib.rs: eof is not in the baseline")
+            .at(Location::parse("src/lib.rs"))
+            .with("configuration", "opt-level=3 lto=off"));
+        report.findings.push(Finding::new(Rule::UnjustifiedUnchecked, "`unwrap_debug_checked` has no `SAFETY:` comment")
+            .at(Location::new("/src/dep/src/lib.rs", 4, 9)));
+        report
+    }
+
+    #[test]
+    fn locations() {
+        assert_eq!(Location::parse("src/a.rs:3:5"), Location::new("src/a.rs", 3, 5));
+        assert_eq!(Location::parse("src/a.rs:3"), Location { file: "src/a.rs".into(), line: Some(3), column: None });
+        assert_eq!(Location::parse("C:\\a.rs").file, "C:\\a.rs");
+        assert_eq!(Location::parse("C:\\a.rs").uri(), "file:///C:/a.rs");
+        assert_eq!(Location::parse("src/a.rs:3").to_string(), "src/a.rs:3");
+        assert_eq!(report().findings[3].to_string(), "/src/dep/src/lib.rs:4:9: `unwrap_debug_checked` has no `SAFETY:` comment");
+    }
+
+    /// Compare `actual` to the golden file at `path`, or overwrite it with
+    /// `REACHABILITY_BLESS=1`.
+    fn golden(path: &str, actual: String) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+        if std::env::var_os("REACHABILITY_BLESS").is_some() {
+            std::fs::write(&path, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(actual == expected, "`{}` differs, rerun with REACHABILITY_BLESS=1 to update it:\n{}", path.display(), actual);
+    }
+
+    #[test]
+    fn formats() {
+        let report = report();
+        golden("tests/golden/report.json", format!("{:#}\n", report.to_json()));
+        golden("tests/golden/report.sarif", format!("{:#}\n", report.to_sarif()));
+    }
+}
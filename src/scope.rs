@@ -0,0 +1,173 @@
+//! Per-crate enforcement, chosen by the final binary.
+//!
+//! Cargo features unify across the dependency graph, so a library enabling
+//! `static` would enforce every crate's sites for everyone. Libraries can
+//! instead select a mode for their own sites with `--cfg reachability="..."`
+//! from their build script (see `unreachable_static!`), and the binary picks
+//! the crates it holds to their proofs by setting `REACHABILITY_ENFORCE` for
+//! `reachability-ld`, e.g. `REACHABILITY_ENFORCE=app,parser`:
+//!
+//! - Surviving link-time sites of other crates are linked to runtime
+//!   fallbacks, as with `REACHABILITY_FALLBACK` (see [`crate::fallback`]),
+//!   and behave like `std::unreachable!()`.
+//! - Surviving `warn` sites (see [`crate::registry`]) of the listed crates
+//!   fail the link.
+//!
+//! Sites are matched on the crate name at the start of their module path.
+//! `*` enforces every crate, which is the default without the variable.
+//!
+//! The scope can also come from the linker arguments [`ENFORCE_ARG`],
+//! [`ALLOW_ARG`] and [`ALLOW_NAME_ARG`], which `reachability::build` passes
+//! for `reachability.toml`.
+//! Allowed sites are never enforced, and the variable takes precedence over
+//! the arguments' crates.
+
+use std::ffi::OsString;
+use std::io;
+use crate::{ld, symbol};
+
+/// The environment variable `reachability-ld` reads the scope from.
+pub const SCOPE_VAR: &str = "REACHABILITY_ENFORCE";
+
+/// Linker argument prefix listing the enforced crates.
+pub const ENFORCE_ARG: &str = "--reachability-enforce=";
+
+/// Linker argument prefix naming a site that isn't enforced, as a pattern for
+/// [`symbol::Site::matches`].
+pub const ALLOW_ARG: &str = "--reachability-allow=";
+
+/// Linker argument prefix naming a site that isn't enforced by its message.
+pub const ALLOW_NAME_ARG: &str = "--reachability-allow-name=";
+
+/// The crates whose sites are enforced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    /// Crate names, or `None` for every crate.
+    crates: Option<Vec<String>>,
+    /// Site patterns that are never enforced.
+    allowed: Vec<String>,
+    /// Messages of sites that are never enforced.
+    allowed_names: Vec<String>,
+}
+
+impl Scope {
+    /// Enforce every crate.
+    pub fn all() -> Self {
+        Scope { crates: None, allowed: Vec::new(), allowed_names: Vec::new() }
+    }
+
+    /// Parse a comma separated list of crate names, or `*` for every crate.
+    /// Package names are accepted too, with `-` in place of `_`.
+    pub fn parse(list: &str) -> Self {
+        let mut scope = Scope::all();
+        scope.enforce(list);
+        scope
+    }
+
+    /// Enforce only the crates in `list`, as for [`Scope::parse`].
+    pub fn enforce(&mut self, list: &str) {
+        let mut crates = Vec::new();
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            if name == "*" {
+                self.crates = None;
+                return;
+            }
+            crates.push(name.replace('-', "_"));
+        }
+        self.crates = Some(crates);
+    }
+
+    /// Never enforce the sites matching `pattern`.
+    pub fn allow(&mut self, pattern: &str) {
+        self.allowed.push(pattern.to_owned());
+    }
+
+    /// Never enforce the sites with the message `name`.
+    pub fn allow_name(&mut self, name: &str) {
+        self.allowed_names.push(name.to_owned());
+    }
+
+    /// Remove the [`ENFORCE_ARG`], [`ALLOW_ARG`] and [`ALLOW_NAME_ARG`]
+    /// arguments from `args` and apply them. Response files are expanded if
+    /// they contain any.
+    pub fn take_link_args(&mut self, args: Vec<OsString>) -> io::Result<Vec<OsString>> {
+        let is_scope = |arg: &OsString| arg.to_str().is_some_and(|a| {
+            a.starts_with(ENFORCE_ARG) || a.starts_with(ALLOW_ARG) || a.starts_with(ALLOW_NAME_ARG)
+        });
+        let expanded = ld::expand_args(&args)?;
+        if !expanded.iter().any(is_scope) {
+            return Ok(args);
+        }
+
+        let mut rest = Vec::new();
+        for arg in expanded {
+            match arg.to_str() {
+                Some(a) if a.starts_with(ENFORCE_ARG) => self.enforce(&a[ENFORCE_ARG.len()..]),
+                Some(a) if a.starts_with(ALLOW_ARG) => self.allow(&a[ALLOW_ARG.len()..]),
+                Some(a) if a.starts_with(ALLOW_NAME_ARG) => self.allow_name(&a[ALLOW_NAME_ARG.len()..]),
+                _ => rest.push(arg),
+            }
+        }
+        Ok(rest)
+    }
+
+    /// Whether every crate is enforced.
+    pub fn is_all(&self) -> bool {
+        self.crates.is_none()
+    }
+
+    /// Whether sites of the crate `name` are enforced.
+    pub fn includes_crate(&self, name: &str) -> bool {
+        match &self.crates {
+            Some(crates) => crates.iter().any(|c| c == name),
+            None => true,
+        }
+    }
+
+    /// Whether the site with link name `symbol` is enforced. Names that
+    /// can't be decoded are always enforced.
+    pub fn includes(&self, symbol: &str) -> bool {
+        match symbol::decode(symbol) {
+            Some(site) => {
+                self.includes_crate(site.crate_name())
+                    && !self.allowed.iter().any(|p| site.matches(p))
+                    && !site.message.is_some_and(|m| self.allowed_names.iter().any(|n| n == m))
+            },
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope() {
+        let scope = Scope::parse("app, my-parser,");
+        assert!(!scope.is_all());
+        assert!(scope.includes("___unreachable_static___@app::net@src/net.rs:3:5"));
+        assert!(scope.includes("___unreachable_static___@my_parser@src/lib.rs:7:9: eof"));
+        assert!(!scope.includes("___unreachable_static___@serde::de@src/de.rs:1:1"));
+        assert!(scope.includes("___unreachable_static___@"));
+
+        assert_eq!(Scope::parse("app,*"), Scope::all());
+        assert!(!Scope::parse("").includes_crate("app"));
+    }
+
+    #[test]
+    fn link_args() {
+        let mut scope = Scope::all();
+        let args = [
+            "-o", "app", "--reachability-enforce=app",
+            "--reachability-allow=src/net.rs:3", "--reachability-allow-name=bad header",
+        ];
+        let args = scope.take_link_args(args.iter().map(OsString::from).collect()).unwrap();
+        assert_eq!(args, ["-o", "app"]);
+        assert!(!scope.is_all());
+        assert!(!scope.includes("___unreachable_static___@app::net@src/net.rs:3:5"));
+        assert!(scope.includes("___unreachable_static___@app::net@src/net.rs:4:5"));
+        assert!(!scope.includes("___unreachable_static___@app::net@src/net.rs:4:5: bad header"));
+        assert!(scope.includes("___unreachable_static___@app::net@src/net.rs:4:5: bad"));
+    }
+}
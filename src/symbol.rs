@@ -0,0 +1,196 @@
+//! Link names of `unreachable_static!()` sites.
+//!
+//! Every expansion of `unreachable_static!(!)` references its own undefined
+//! symbol, so a failed link names the exact site that survived optimization.
+//! The name is built from the site's `module_path!()`, `file!()`, `line!()`,
+//! `column!()` and optional message:
+//!
+//! ```text
+//! ___unreachable_static___@<module path>@<file>:<line>:<column>
+//! ___unreachable_static___@<module path>@<file>:<line>:<column>: <message>
+//! ```
+//!
+//! The module path keeps identical file names in different crates (every
+//! crate has a `src/lib.rs`) from sharing a symbol. Targets that prefix C
+//! symbols with an underscore (such as macOS) add one more leading `_`, which
+//! [`decode`] accepts as well.
+
+use core::fmt;
+
+/// The prefix shared by all `unreachable_static!()` link names.
+pub const SYMBOL_PREFIX: &str = "___unreachable_static___@";
+
+/// A source location decoded from an `unreachable_static!()` link name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Site<'a> {
+    /// `module_path!()` of the expansion.
+    pub module_path: &'a str,
+    /// `file!()` of the expansion.
+    pub file: &'a str,
+    /// `line!()` of the expansion.
+    pub line: u32,
+    /// `column!()` of the expansion.
+    pub column: u32,
+    /// The message passed to `unreachable_static!(!: "...")`, if any.
+    pub message: Option<&'a str>,
+}
+
+impl<'a> Site<'a> {
+    /// The name of the crate containing the site.
+    pub fn crate_name(&self) -> &'a str {
+        self.module_path.split("::").next().unwrap_or(self.module_path)
+    }
+
+    /// Whether the site is at `pattern`, one of `<file>`, `<file>:<line>` or
+    /// `<file>:<line>:<column>`. The file matches any path ending with it.
+    ///
+    /// ```
+    /// let site = reachability::symbol::decode("___unreachable_static___@app@src/net.rs:88:13").unwrap();
+    /// assert!(site.matches("net.rs:88"));
+    /// assert!(site.matches("src/net.rs:88:13"));
+    /// assert!(!site.matches("et.rs"));
+    /// ```
+    pub fn matches(&self, pattern: &str) -> bool {
+        let (file, line, column) = match split_number(pattern) {
+            Some((rest, n)) => match split_number(rest) {
+                Some((file, line)) => (file, Some(line), Some(n)),
+                None => (rest, Some(n), None),
+            },
+            None => (pattern, None, None),
+        };
+        let file_matches = match self.file.strip_suffix(file) {
+            Some("") => true,
+            Some(dir) => dir.ends_with('/') || dir.ends_with('\\'),
+            None => false,
+        };
+        file_matches && line.map_or(true, |l| l == self.line) && column.map_or(true, |c| c == self.column)
+    }
+}
+
+impl fmt::Display for Site<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)?;
+        if let Some(message) = self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `symbol` is the link name of an `unreachable_static!()` site.
+pub fn is_marker(symbol: &str) -> bool {
+    strip_prefix(symbol).is_some()
+}
+
+/// Whether two link names are of the same site, such as the ELF and the
+/// macOS name of one site.
+pub fn same_site(a: &str, b: &str) -> bool {
+    match (decode(a), decode(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Decode an `unreachable_static!()` link name back into its source location.
+///
+/// Returns `None` if `symbol` doesn't follow the naming scheme.
+///
+/// ```
+/// let site = reachability::symbol::decode(
+///     "___unreachable_static___@app::net@src/net.rs:88:13: bad header",
+/// ).unwrap();
+/// assert_eq!(site.crate_name(), "app");
+/// assert_eq!((site.file, site.line, site.column), ("src/net.rs", 88, 13));
+/// assert_eq!(site.message, Some("bad header"));
+/// ```
+pub fn decode(symbol: &str) -> Option<Site<'_>> {
+    let rest = strip_prefix(symbol)?;
+    let at = rest.find('@')?;
+    let (module_path, rest) = (&rest[..at], &rest[at + 1..]);
+    if module_path.is_empty() {
+        return None;
+    }
+
+    // The file name is free-form, so take the first `:<line>:<column>` that
+    // ends the name or is followed by a message.
+    let mut search = 0;
+    while let Some(colon) = rest[search..].find(':') {
+        let colon = search + colon;
+        if let Some((line, column, message)) = location(&rest[colon + 1..]) {
+            return Some(Site {
+                module_path,
+                file: &rest[..colon],
+                line,
+                column,
+                message,
+            });
+        }
+        search = colon + 1;
+    }
+
+    None
+}
+
+fn strip_prefix(symbol: &str) -> Option<&str> {
+    symbol.strip_prefix(SYMBOL_PREFIX)
+        .or_else(|| symbol.strip_prefix('_')?.strip_prefix(SYMBOL_PREFIX))
+}
+
+/// Parse `<line>:<column>` optionally followed by `: <message>`.
+fn location(s: &str) -> Option<(u32, u32, Option<&str>)> {
+    let (line, s) = number(s)?;
+    let (column, s) = number(s.strip_prefix(':')?)?;
+    match s {
+        "" => Some((line, column, None)),
+        _ => Some((line, column, Some(s.strip_prefix(": ")?))),
+    }
+}
+
+/// Split `<s>:<number>`.
+fn split_number(s: &str) -> Option<(&str, u32)> {
+    let (rest, n) = s.rsplit_once(':')?;
+    Some((rest, n.parse().ok()?))
+}
+
+fn number(s: &str) -> Option<(u32, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    Some((s[..end].parse().ok()?, &s[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let (line, symbol) = (line!(), crate::internal_unreachable_static_symbol!("a: b"));
+        let site = decode(symbol).unwrap();
+        assert_eq!(site.module_path, module_path!());
+        assert_eq!(site.crate_name(), "reachability");
+        assert_eq!(site.file, file!());
+        assert_eq!(site.line, line);
+        assert_eq!(site.message, Some("a: b"));
+
+        let site = decode(crate::internal_unreachable_static_symbol!()).unwrap();
+        assert_eq!(site.message, None);
+    }
+
+    #[test]
+    fn file_names() {
+        let site = decode("____unreachable_static___@a@C:\\x:1:y.rs:12:5").unwrap();
+        assert_eq!((site.file, site.line, site.column), ("C:\\x:1:y.rs", 12, 5));
+        assert_eq!(site.to_string(), "C:\\x:1:y.rs:12:5");
+
+        assert!(decode("___unreachable_static___").is_none());
+        assert!(decode("___unreachable_static___@a@src/lib.rs:12").is_none());
+        assert!(decode("___unreachable_static___@@src/lib.rs:12:5").is_none());
+        assert!(!is_marker("unreachable_static"));
+    }
+
+    #[test]
+    fn same_sites() {
+        assert!(same_site("___unreachable_static___@app@src/main.rs:3:5", "____unreachable_static___@app@src/main.rs:3:5"));
+        assert!(!same_site("___unreachable_static___@app@src/main.rs:3:5", "___unreachable_static___@lib@src/main.rs:3:5"));
+        assert!(same_site("foo", "foo"));
+    }
+}
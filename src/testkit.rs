@@ -0,0 +1,191 @@
+//! Link tests for crates using `unreachable_static!()`.
+//!
+//! Whether a site is proven can only be seen by linking, so a test can't
+//! check it from inside its own binary. A [`Snippet`] is a crate of its own,
+//! written to a directory and built with cargo under a matrix [`Profile`], for
+//! an integration test to assert that it links or that it fails to link on a
+//! given site:
+//!
+//! ```no_run
+//! use reachability::matrix::{Lto, OptLevel, Profile};
+//! use reachability::testkit::Snippet;
+//!
+//! let snippet = Snippet::main("parse_proven", r#"
+//!     fn main() {
+//!         let n: u8 = std::env::args().count() as u8;
+//!         if n.checked_add(0).is_none() {
+//!             reachability::unreachable_static!();
+//!         }
+//!     }
+//! "#);
+//! snippet.assert_links(Profile { opt_level: OptLevel::O3, lto: Lto::Fat, debug_assertions: false });
+//! snippet.assert_fails_at(Profile { opt_level: OptLevel::O0, lto: Lto::Off, debug_assertions: false }, "src/main.rs:5");
+//! ```
+//!
+//! Snippets depend on this crate with the `static` feature, and on whatever
+//! else [`Snippet::dependency`] adds, such as the crate under test by path.
+//! Cargo runs `--offline` in the snippet's directory, so the `.cargo/config.toml`
+//! files above it apply: placing snippets below the workspace, for example in
+//! `CARGO_TARGET_TMPDIR` with [`Snippet::dir`], builds them from the same
+//! vendored sources as the workspace. `RUSTFLAGS` applies to snippets too.
+//!
+//! By default snippets are written to a directory of their own for each test
+//! process, so test binaries running at once can't overwrite each other's
+//! snippets, at the cost of building their dependencies again every run.
+//! `CARGO_TARGET_TMPDIR` keeps the builds between runs.
+
+use std::{env, fmt, fs, io, process};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::matrix::{self, Outcome, Profile};
+
+/// A crate with one binary or test target, built to check its sites.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    name: String,
+    target: Target,
+    dependencies: Vec<String>,
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    /// The source of `src/main.rs`.
+    Main(String),
+    /// The path of an integration test.
+    Test(PathBuf),
+}
+
+impl Snippet {
+    /// A crate named `name` with `source` as its `main.rs`. Sites in it are
+    /// at `src/main.rs`.
+    pub fn main(name: &str, source: &str) -> Self {
+        Snippet::new(name, Target::Main(source.into()))
+    }
+
+    /// A crate named `name` building the integration test at `path`, such as
+    /// `concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fail.rs")`.
+    pub fn test(name: &str, path: impl Into<PathBuf>) -> Self {
+        Snippet::new(name, Target::Test(path.into()))
+    }
+
+    fn new(name: &str, target: Target) -> Self {
+        let reachability = format!("reachability = {{ path = {}, features = [\"static\"] }}", quote(Path::new(env!("CARGO_MANIFEST_DIR"))));
+        let dir = env::temp_dir().join(format!("reachability-testkit-{}", process::id()));
+        Snippet { name: name.into(), target, dependencies: vec![reachability], dir }
+    }
+
+    /// Add a `[dependencies]` entry, like `app = { path = "/src/app" }`,
+    /// replacing any entry with the same name. Replacing `reachability`
+    /// changes its features.
+    pub fn dependency(mut self, entry: &str) -> Self {
+        let name = |entry: &str| entry.split('=').next().unwrap_or("").trim().to_owned();
+        self.dependencies.retain(|d| name(d) != name(entry));
+        self.dependencies.push(entry.into());
+        self
+    }
+
+    /// Write the crate to `dir/<name>` rather than below the system's
+    /// temporary directory, such as `env!("CARGO_TARGET_TMPDIR")`. Snippets in
+    /// the same `dir` share a target directory, so names must be unique
+    /// within it, across every test binary using it.
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// The manifest of the crate.
+    fn manifest(&self) -> String {
+        let target = match &self.target {
+            Target::Main(_) => format!("[[bin]]\nname = {:?}\npath = \"src/main.rs\"", self.name),
+            Target::Test(path) => format!("[[test]]\nname = {:?}\npath = {}", self.name, quote(path)),
+        };
+        format!("[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\nautobins = false\nautotests = false\n\n\
+            {}\n\n[dependencies]\n{}\n\n# Not part of any workspace above\n[workspace]\n", self.name, target, self.dependencies.join("\n"))
+    }
+
+    /// Build the crate under `profile`. Returns the outcome of its target, or
+    /// of the first dependency that failed to build.
+    pub fn build(&self, profile: Profile) -> io::Result<Outcome> {
+        let dir = self.dir.join(&self.name);
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(dir.join("Cargo.toml"), self.manifest())?;
+        if let Target::Main(source) = &self.target {
+            fs::write(dir.join("src/main.rs"), source)?;
+        }
+
+        let target_dir = self.dir.join("target");
+        let build = matrix::build_in(&dir, profile, &[OsString::from("--offline")], &[("CARGO_TARGET_DIR", target_dir.into())])?;
+        let mut outcome = None;
+        for (label, result) in build.results.targets {
+            match result {
+                Outcome::Failed(_) => return Ok(result),
+                _ if label == self.name => outcome = Some(result),
+                _ => (),
+            }
+        }
+        outcome.ok_or_else(|| io::Error::other(format!("cargo didn't build `{}`, see `cargo build` in `{}`", self.name, dir.display())))
+    }
+
+    /// Assert that the crate links under `profile`.
+    pub fn assert_links(&self, profile: Profile) {
+        match self.build(profile) {
+            Ok(Outcome::Linked) => (),
+            Ok(outcome) => panic!("`{}` didn't link with {}: {}", self.name, profile, Failure(&outcome)),
+            Err(e) => panic!("failed to build `{}` with {}: {}", self.name, profile, e),
+        }
+    }
+
+    /// Assert that the crate fails to link under `profile` because of the
+    /// site at `site`, given as `<file>`, `<file>:<line>` or
+    /// `<file>:<line>:<column>` (see [`crate::symbol::Site::matches`]).
+    /// Other sites may fail too.
+    pub fn assert_fails_at(&self, profile: Profile, site: &str) {
+        match self.build(profile) {
+            Ok(Outcome::LinkFailed(diagnostics)) if diagnostics.iter().any(|d| d.site().is_some_and(|s| s.matches(site))) => (),
+            Ok(outcome) => panic!("`{}` didn't fail to link at `{}` with {}: {}", self.name, site, profile, Failure(&outcome)),
+            Err(e) => panic!("failed to build `{}` with {}: {}", self.name, profile, e),
+        }
+    }
+}
+
+/// A TOML literal string holding `path`.
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.display())
+}
+
+/// Describes an unexpected outcome.
+struct Failure<'a>(&'a Outcome);
+
+impl fmt::Display for Failure<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Outcome::Linked => write!(f, "it linked"),
+            Outcome::LinkFailed(diagnostics) if diagnostics.is_empty() => write!(f, "it failed to link without naming any sites"),
+            Outcome::LinkFailed(diagnostics) => {
+                write!(f, "it failed to link at")?;
+                for site in diagnostics.iter().filter_map(|d| d.site()) {
+                    write!(f, "\n    {}", site)?;
+                }
+                Ok(())
+            },
+            Outcome::Failed(error) => write!(f, "it failed to build: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let snippet = Snippet::test("fail", "/src/app/tests/fail.rs")
+            .dependency("app = { path = '/src/app' }")
+            .dependency("reachability = { path = '/src/reachability', features = [\"warn\"] }");
+        assert_eq!(snippet.manifest(), "[package]\nname = \"fail\"\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\nautobins = false\nautotests = false\n\n\
+            [[test]]\nname = \"fail\"\npath = '/src/app/tests/fail.rs'\n\n\
+            [dependencies]\napp = { path = '/src/app' }\nreachability = { path = '/src/reachability', features = [\"warn\"] }\n\n\
+            # Not part of any workspace above\n[workspace]\n");
+    }
+}
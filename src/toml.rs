@@ -0,0 +1,406 @@
+//! Just enough TOML for reading `reachability.toml`.
+//!
+//! Supports tables, arrays of tables, dotted keys, basic and literal strings,
+//! integers, booleans, arrays and inline tables. Multi-line strings, floats
+//! and dates are rejected.
+
+use std::fmt;
+
+/// A parsed TOML value. Tables keep their keys in document order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+    Table(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Look up `key` in a table.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Table(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Value] {
+        match self {
+            Value::Array(values) => values,
+            _ => &[],
+        }
+    }
+
+    /// The entries of a table.
+    pub fn as_table(&self) -> &[(String, Value)] {
+        match self {
+            Value::Table(entries) => entries,
+            _ => &[],
+        }
+    }
+}
+
+/// A syntax error, with the line it was found on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid TOML at line {}", self.line)
+    }
+}
+
+impl std::error::Error for Error { }
+
+/// Parse a TOML document into its root table.
+pub fn parse(s: &str) -> Result<Value, Error> {
+    let mut parser = Parser { s: s.as_bytes(), pos: 0 };
+    let mut root = Vec::new();
+    let mut current = Vec::new();
+    loop {
+        parser.blank_lines();
+        match parser.s.get(parser.pos) {
+            None => break,
+            Some(b'[') => {
+                parser.pos += 1;
+                let array = parser.s.get(parser.pos) == Some(&b'[');
+                if array {
+                    parser.pos += 1;
+                }
+                let path = parser.key()?;
+                if !parser.eat("]") || (array && !parser.eat("]")) {
+                    return Err(parser.error());
+                }
+                match array {
+                    true => {
+                        let (last, parents) = path.split_last().ok_or_else(|| parser.error())?;
+                        let table = table(&mut root, parents).ok_or_else(|| parser.error())?;
+                        match entry(table, last) {
+                            Some(Value::Array(tables)) => tables.push(Value::Table(Vec::new())),
+                            Some(_) => return Err(parser.error()),
+                            None => table.push((last.clone(), Value::Array(vec![Value::Table(Vec::new())]))),
+                        }
+                    },
+                    false => {
+                        table(&mut root, &path).ok_or_else(|| parser.error())?;
+                    },
+                }
+                current = path;
+            },
+            Some(_) => {
+                let path = parser.key()?;
+                if !parser.eat("=") {
+                    return Err(parser.error());
+                }
+                let value = parser.value()?;
+                let (last, parents) = path.split_last().ok_or_else(|| parser.error())?;
+                let error = parser.error();
+                let table = table(&mut root, &current).and_then(|t| table(t, parents)).ok_or(error)?;
+                insert(table, last, value).ok_or(error)?;
+            },
+        }
+        parser.end_of_line()?;
+    }
+    Ok(Value::Table(root))
+}
+
+fn entry<'a>(table: &'a mut [(String, Value)], key: &str) -> Option<&'a mut Value> {
+    table.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// The table at `path` below `root`, created if missing, descending into the
+/// last table of arrays of tables.
+fn table<'a>(mut root: &'a mut Vec<(String, Value)>, path: &[String]) -> Option<&'a mut Vec<(String, Value)>> {
+    for key in path {
+        if entry(root, key).is_none() {
+            root.push((key.clone(), Value::Table(Vec::new())));
+        }
+        root = match entry(root, key)? {
+            Value::Table(entries) => entries,
+            Value::Array(values) => match values.last_mut()? {
+                Value::Table(entries) => entries,
+                _ => return None,
+            },
+            _ => return None,
+        };
+    }
+    Some(root)
+}
+
+fn insert(table: &mut Vec<(String, Value)>, key: &str, value: Value) -> Option<()> {
+    if entry(table, key).is_some() {
+        return None;
+    }
+    table.push((key.to_owned(), value));
+    Some(())
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> Error {
+        let line = self.s[..self.pos.min(self.s.len())].iter().filter(|&&b| b == b'\n').count();
+        Error { line: line + 1 }
+    }
+
+    /// Skip spaces, tabs and a comment on the current line.
+    fn whitespace(&mut self) {
+        while self.s.get(self.pos).is_some_and(|&b| b == b' ' || b == b'\t') {
+            self.pos += 1;
+        }
+        if self.s.get(self.pos) == Some(&b'#') {
+            while self.s.get(self.pos).is_some_and(|&b| b != b'\n') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skip whitespace, comments and newlines.
+    fn blank_lines(&mut self) {
+        loop {
+            self.whitespace();
+            match self.s.get(self.pos) {
+                Some(b'\n') => self.pos += 1,
+                Some(b'\r') if self.s.get(self.pos + 1) == Some(&b'\n') => self.pos += 2,
+                _ => break,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), Error> {
+        self.whitespace();
+        match self.s.get(self.pos) {
+            None | Some(b'\n') | Some(b'\r') => {
+                self.blank_lines();
+                Ok(())
+            },
+            _ => Err(self.error()),
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.whitespace();
+        let matches = self.s[self.pos..].starts_with(token.as_bytes());
+        if matches {
+            self.pos += token.len();
+        }
+        matches
+    }
+
+    /// A dotted key.
+    fn key(&mut self) -> Result<Vec<String>, Error> {
+        let mut path = Vec::new();
+        loop {
+            self.whitespace();
+            let key = match self.s.get(self.pos) {
+                Some(b'"') => self.basic_string()?,
+                Some(b'\'') => self.literal_string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.s.get(self.pos).is_some_and(|&b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err(self.error());
+                    }
+                    String::from_utf8_lossy(&self.s[start..self.pos]).into_owned()
+                },
+            };
+            path.push(key);
+            if !self.eat(".") {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.whitespace();
+        match self.s.get(self.pos) {
+            Some(b'"') if self.s[self.pos..].starts_with(b"\"\"\"") => Err(self.error()),
+            Some(b'\'') if self.s[self.pos..].starts_with(b"'''") => Err(self.error()),
+            Some(b'"') => self.basic_string().map(Value::String),
+            Some(b'\'') => self.literal_string().map(Value::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                loop {
+                    self.blank_lines();
+                    if self.eat("]") {
+                        break;
+                    }
+                    values.push(self.value()?);
+                    self.blank_lines();
+                    if self.eat("]") {
+                        break;
+                    } else if !self.eat(",") {
+                        return Err(self.error());
+                    }
+                }
+                Ok(Value::Array(values))
+            },
+            Some(b'{') => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                if !self.eat("}") {
+                    loop {
+                        let path = self.key()?;
+                        if !self.eat("=") {
+                            return Err(self.error());
+                        }
+                        let value = self.value()?;
+                        let (last, parents) = path.split_last().ok_or_else(|| self.error())?;
+                        let error = self.error();
+                        insert(table(&mut entries, parents).ok_or(error)?, last, value).ok_or(error)?;
+                        if self.eat("}") {
+                            break;
+                        } else if !self.eat(",") {
+                            return Err(self.error());
+                        }
+                    }
+                }
+                Ok(Value::Table(entries))
+            },
+            Some(b'+') | Some(b'-') | Some(b'0'..=b'9') => {
+                let start = self.pos;
+                while self.s.get(self.pos).is_some_and(|&b| b.is_ascii_alphanumeric() || b"+-_.:".contains(&b)) {
+                    self.pos += 1;
+                }
+                let number = std::str::from_utf8(&self.s[start..self.pos]).unwrap_or("");
+                let digits = number.trim_start_matches(['+', '-']);
+                if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+                    return Err(self.error());
+                }
+                number.replace('_', "").parse().map(Value::Integer).map_err(|_| self.error())
+            },
+            _ if self.eat("true") => Ok(Value::Bool(true)),
+            _ if self.eat("false") => Ok(Value::Bool(false)),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let b = *self.s.get(self.pos).ok_or_else(|| self.error())?;
+            self.pos += 1;
+            match b {
+                b'"' => break,
+                b'\n' => return Err(self.error()),
+                b'\\' => {
+                    let escape = *self.s.get(self.pos).ok_or_else(|| self.error())?;
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode(4)?,
+                        b'U' => self.unicode(8)?,
+                        _ => return Err(self.error()),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                b => bytes.push(b),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error())
+    }
+
+    fn literal_string(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+        let start = self.pos;
+        while self.s.get(self.pos).is_some_and(|&b| b != b'\'' && b != b'\n') {
+            self.pos += 1;
+        }
+        if self.s.get(self.pos) != Some(&b'\'') {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        String::from_utf8(self.s[start..self.pos - 1].to_vec()).map_err(|_| self.error())
+    }
+
+    fn unicode(&mut self, len: usize) -> Result<char, Error> {
+        let c = self.s.get(self.pos..self.pos + len)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error())?;
+        self.pos += len;
+        Ok(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document() {
+        let value = parse(r#"
+            # comment
+            mode = "static" # trailing comment
+            "quoted key" = 'C:\path'
+            enforce = [
+                "app",  # first
+                "parser",
+            ]
+            count = -1_000
+
+            [toolchain]
+            rustc = "1.80\u00e9"
+            opt.level = { min = 2, lto = true }
+
+            [[allow]]
+            site = "src/a.rs:1"
+            [[allow]]
+            site = "src/b.rs:2"
+        "#).unwrap();
+        assert_eq!(value.get("mode").and_then(Value::as_str), Some("static"));
+        assert_eq!(value.get("quoted key").and_then(Value::as_str), Some("C:\\path"));
+        assert_eq!(value.get("enforce").map(Value::as_array).map(<[_]>::len), Some(2));
+        assert_eq!(value.get("count").and_then(Value::as_integer), Some(-1000));
+        let toolchain = value.get("toolchain").unwrap();
+        assert_eq!(toolchain.get("rustc").and_then(Value::as_str), Some("1.80\u{e9}"));
+        let opt = toolchain.get("opt").and_then(|o| o.get("level")).unwrap();
+        assert_eq!(opt.get("min").and_then(Value::as_integer), Some(2));
+        assert_eq!(opt.get("lto").and_then(Value::as_bool), Some(true));
+        let allow = value.get("allow").map(Value::as_array).unwrap();
+        assert_eq!(allow[1].get("site").and_then(Value::as_str), Some("src/b.rs:2"));
+
+        assert_eq!(parse("a = 1\na = 2"), Err(Error { line: 2 }));
+        assert_eq!(parse("a = 1.5"), Err(Error { line: 1 }));
+        assert_eq!(parse("a = \"x\" b"), Err(Error { line: 1 }));
+        assert_eq!(parse("\n\na = [1,\n"), Err(Error { line: 4 }));
+    }
+}
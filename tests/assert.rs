@@ -0,0 +1,11 @@
+#[test]
+#[allow(clippy::const_is_empty)]
+fn assert_static() {
+    let v = [0];
+    reachability::assert_static!(!v.is_empty());
+    reachability::assert_static!(v.len() == 1, "one element");
+    reachability::assert_eq_static!(v.len(), 1);
+    reachability::assert_ne_static!(v[0], 1, "{} isn't 1", v[0]);
+    reachability::assert_matches_static!(v.first(), Some(0) | Some(2));
+    reachability::assert_matches_static!(v.first(), Some(&x) if x < 1, "guarded");
+}
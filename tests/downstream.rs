@@ -0,0 +1,50 @@
+#![cfg(feature = "std")]
+
+use reachability::matrix::{Lto, OptLevel, Outcome, Profile};
+use reachability::testkit::Snippet;
+
+/// Uses every macro from a crate that denies warnings and doesn't declare
+/// the `reachability` cfgs.
+const SOURCE: &str = r#"
+#![deny(warnings)]
+
+use reachability::OptionExt;
+
+fn main() {
+    let v = [1u8, 2];
+    let n = std::env::args().count();
+    if v.is_empty() {
+        reachability::unreachable_static!("empty");
+    }
+    reachability::assert_static!(v.len() == 2);
+    let first = *reachability::unwrap_static!(v.first());
+    let sum = reachability::checked_ops!(v[0] + v[1]);
+    // Safety: the sum is 3
+    let unchecked = unsafe { reachability::unchecked_ops!(v[0] + v[1]) };
+    if n == 0 {
+        // Safety: the program name is always an argument
+        unsafe { reachability::unreachable_unchecked!("no arguments") }
+    }
+    // Safety: `v` isn't empty
+    let last = unsafe { *v.last().unwrap_debug_checked() };
+    println!("{} {} {} {}", first, sum, unchecked, last);
+}
+"#;
+
+#[test]
+fn no_warnings() {
+    let snippet = Snippet::main("downstream_warnings", SOURCE).dir(env!("CARGO_TARGET_TMPDIR"));
+    snippet.assert_links(Profile { opt_level: OptLevel::O0, lto: Lto::Off, debug_assertions: true });
+    snippet.assert_links(Profile { opt_level: OptLevel::O3, lto: Lto::Off, debug_assertions: false });
+}
+
+/// The macros don't hide lints on the caller's tokens.
+#[test]
+fn caller_lints() {
+    let source = "#![deny(unreachable_code)]\n\nfn main() {\n    reachability::assert_static!({ return; true });\n}\n";
+    let snippet = Snippet::main("downstream_lints", source).dir(env!("CARGO_TARGET_TMPDIR"));
+    match snippet.build(Profile { opt_level: OptLevel::O0, lto: Lto::Off, debug_assertions: true }).unwrap() {
+        Outcome::Failed(message) => assert!(message.contains("unreachable expression"), "{}", message),
+        outcome => panic!("{:?}", outcome),
+    }
+}
@@ -0,0 +1,20 @@
+#![cfg(feature = "std")]
+
+use reachability::matrix::{Lto, OptLevel, Outcome, Profile};
+use reachability::testkit::Snippet;
+
+/// More sites than lld reports errors for by default.
+#[test]
+fn every_site_reported() {
+    let mut source = String::from("fn main() {\n    let n = std::env::args().count();\n");
+    for i in 0..25 {
+        source.push_str(&format!("    if n == {} {{\n        reachability::unreachable_static!();\n    }}\n", i + 100));
+    }
+    source.push_str("}\n");
+
+    let snippet = Snippet::main("error_limit", &source).dir(env!("CARGO_TARGET_TMPDIR"));
+    match snippet.build(Profile { opt_level: OptLevel::O3, lto: Lto::Off, debug_assertions: false }).unwrap() {
+        Outcome::LinkFailed(diagnostics) => assert_eq!(diagnostics.len(), 25),
+        outcome => panic!("{:?}", outcome),
+    }
+}
@@ -2,6 +2,7 @@
 
 #[test]
 #[cfg(feature = "unstable")]
+#[allow(clippy::single_match)]
 fn black_box() {
     extern crate test;
     use test::black_box;
@@ -13,6 +14,7 @@ fn black_box() {
 }
 
 #[test]
+#[allow(clippy::single_match)]
 fn black_box_stable() {
     fn blackish_box<T>(dummy: T) -> T {
         unsafe {
@@ -0,0 +1,80 @@
+#![cfg(feature = "std")]
+
+//! `reachability-ld` with `REACHABILITY_FALLBACK` and scopes, linking through
+//! lld, built with cargo directly rather than through `reachability::testkit`,
+//! whose builds retry with the linker's error limit lifted themselves.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// More sites than lld reports errors for by default.
+const SITES: usize = 26;
+
+/// The line of site `i`.
+fn line(i: usize) -> usize {
+    4 + 3 * i
+}
+
+/// Build a crate named `name` with `SITES` sites at O3, linking through
+/// `reachability-ld` with `envs` and `link_args`.
+fn build(name: &str, envs: &[(&str, &str)], link_args: &[String]) -> (PathBuf, Output) {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fallback").join(name);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("Cargo.toml"), format!("[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\n\n\
+        [dependencies]\nreachability = {{ path = '{}', features = [\"static\"] }}\n\n[workspace]\n", name, env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let mut source = String::from("fn main() {\n    let n = std::env::args().count();\n");
+    for i in 0..SITES {
+        source.push_str(&format!("    if n == {} {{\n        reachability::unreachable_static!();\n    }}\n", i + 2));
+    }
+    source.push_str("}\n");
+    fs::write(dir.join("src/main.rs"), source).unwrap();
+
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let print = |args: &[&str]| String::from_utf8(Command::new(&rustc).args(args).output().unwrap().stdout).unwrap();
+    let host = print(&["-vV"]).lines()
+        .find_map(|line| line.strip_prefix("host: ").map(str::to_owned))
+        .unwrap();
+    // The `ld.lld` shipped with rustc, for `cc -fuse-ld=lld`
+    let gcc_ld = PathBuf::from(print(&["--print", "sysroot"]).trim()).join("lib/rustlib").join(&host).join("bin/gcc-ld");
+    let mut rustflags = vec!["-C".to_owned(), "linker-flavor=gcc".to_owned(), "-C".to_owned(), "link-arg=-fuse-ld=lld".to_owned(),
+        "-C".to_owned(), format!("link-arg=-B{}", gcc_ld.display())];
+    for arg in link_args {
+        rustflags.extend(["-C".to_owned(), format!("link-arg={}", arg)]);
+    }
+
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["build", "--release", "--offline"])
+        .current_dir(&dir)
+        .env(format!("CARGO_TARGET_{}_LINKER", host.to_uppercase().replace(['-', '.'], "_")), env!("CARGO_BIN_EXE_reachability-ld"))
+        .env("CARGO_PROFILE_RELEASE_OPT_LEVEL", "3")
+        .env("CARGO_ENCODED_RUSTFLAGS", rustflags.join("\x1f"))
+        .env("REACHABILITY_LINKER", "cc")
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap();
+    (dir.join("target/release").join(name), output)
+}
+
+#[test]
+fn every_site_defined() {
+    let (binary, output) = build("every_site_defined", &[("REACHABILITY_FALLBACK", "1")], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let last = Command::new(&binary).args(vec!["x"; SITES]).output().unwrap();
+    assert!(!last.status.success());
+    assert!(String::from_utf8_lossy(&last.stderr).contains(&format!("src/main.rs:{}:", line(SITES - 1))), "{}", String::from_utf8_lossy(&last.stderr));
+}
+
+/// Allowed sites get fallbacks, and only the enforced one is reported.
+#[test]
+fn enforced_site_reported() {
+    let allowed: Vec<String> = (0..SITES - 1).map(|i| format!("--reachability-allow=src/main.rs:{}", line(i))).collect();
+    let (_, output) = build("enforced_site_reported", &[], &allowed);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("`unreachable_static!()` was not eliminated").count(), 1, "{}", stderr);
+    assert!(stderr.contains(&format!("src/main.rs:{}:", line(SITES - 1))), "{}", stderr);
+}
@@ -1,5 +1,6 @@
 #[test]
 #[cfg(feature = "unstable-internal-test")]
+#[allow(clippy::single_match)]
 fn opt_level_lto() {
     match reachability::tests::grey_box(1) {
         0 => reachability::unreachable_static!(),
@@ -0,0 +1,30 @@
+#[test]
+fn checked_ops() {
+    let v = [200u8, 55];
+    assert_eq!(reachability::checked_ops!(v[0] + v[1] - 5 * 2), 245);
+    assert_eq!(reachability::checked_ops!(-(v[1] as i8) % 7 << 4), -96);
+    assert_eq!(reachability::checked_ops!(v.len() * 4 / v.len()), 4);
+    assert_eq!(reachability::checked_ops!("7".parse::<u32>().unwrap() * 2), 14);
+}
+
+/// Operands and their operators are rewritten a pair at a time, so a hundred
+/// fit in the default `recursion_limit`.
+#[test]
+fn long_expression() {
+    let x = 1u32;
+    assert_eq!(reachability::checked_ops!(
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x
+    ), 100);
+    // Safety: the sum is 100
+    assert_eq!(unsafe { reachability::unchecked_ops!(
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+        x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x + x
+    ) }, 100);
+}
@@ -1,4 +1,5 @@
 #[test]
+#[allow(clippy::single_match, clippy::const_is_empty)]
 fn opt_level_1() {
     match [0].is_empty() {
         true => reachability::unreachable_static!(),
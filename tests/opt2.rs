@@ -1,4 +1,5 @@
 #[test]
+#[allow(clippy::single_match, clippy::needless_option_take)]
 fn opt_level_2() {
     match Some(1).take() {
         Some(0) => reachability::unreachable_static!(),
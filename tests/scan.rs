@@ -0,0 +1,114 @@
+#![cfg(feature = "std")]
+
+//! `reachability::object` and its DWARF reader on objects emitted by rustc.
+
+use std::path::PathBuf;
+use std::process::Command;
+use reachability::object::{self, Reference};
+
+const SOURCE: &str = r#"
+extern "C-unwind" {
+    #[link_name = "___unreachable_static___@fixture@src/lib.rs:11:18: too big"]
+    fn site(symbol: *const u8, len: usize) -> !;
+}
+
+#[inline(always)]
+fn check(n: usize) {
+    if n > 9 {
+        // the site
+        unsafe { site(b"".as_ptr(), 0) }
+    }
+}
+
+#[inline(always)]
+fn middle(n: usize) {
+    check(n.wrapping_mul(3))
+}
+
+#[no_mangle]
+pub fn entry(n: usize) {
+    middle(n)
+}
+"#;
+
+/// Compile `SOURCE` to an object with `flags`.
+fn compile(name: &str, flags: &[&str]) -> Vec<u8> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("scan").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lib.rs"), SOURCE).unwrap();
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let status = Command::new(rustc)
+        .args(["--crate-type=lib", "--crate-name=fixture", "--edition=2021", "--emit=obj", "-Copt-level=3", "-Cdebuginfo=line-tables-only"])
+        .args(flags)
+        .arg(format!("--remap-path-prefix={}=src", dir.display()))
+        .arg("-o").arg(dir.join("fixture.o"))
+        .arg(dir.join("lib.rs"))
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    std::fs::read(dir.join("fixture.o")).unwrap()
+}
+
+fn site(references: &[Reference]) -> &Reference {
+    match references {
+        [reference] => reference,
+        _ => panic!("{:#?}", references),
+    }
+}
+
+fn functions(reference: &Reference) -> Vec<&str> {
+    reference.frames.iter().map(|frame| frame.function.as_deref().unwrap_or("")).collect()
+}
+
+#[test]
+fn dwarf_4() {
+    let references = object::scan(&compile("dwarf-4", &["-Cdwarf-version=4"])).unwrap();
+    let reference = site(&references);
+    assert_eq!(reference.symbol, "___unreachable_static___@fixture@src/lib.rs:11:18: too big");
+    assert_eq!(reference.function.as_deref(), Some("entry"));
+    assert_eq!(functions(reference), ["check", "middle", "entry"]);
+    assert_eq!((reference.frames[1].line, reference.frames[1].column), (17, 5));
+    assert_eq!((reference.frames[2].line, reference.frames[2].column), (22, 5));
+}
+
+#[test]
+fn dwarf_5() {
+    let references = object::scan(&compile("dwarf-5", &["-Cdwarf-version=5"])).unwrap();
+    let reference = site(&references);
+    assert_eq!(functions(reference), ["check", "middle", "entry"]);
+    assert_eq!(reference.frames[0].line, 11);
+    assert_eq!(reference.frames[2].file.as_deref().map(|f| f.ends_with("lib.rs")), Some(true));
+}
+
+/// The inlining is in the `.dwo` file, so only the function is known.
+#[test]
+fn split_dwarf() {
+    let references = object::scan(&compile("split", &["-Csplit-debuginfo=unpacked"])).unwrap();
+    let reference = site(&references);
+    assert_eq!(reference.function.as_deref(), Some("entry"));
+    assert!(reference.frames.len() <= 1, "{:?}", reference.frames);
+}
+
+/// Truncated and corrupted objects are errors or lose information, but
+/// never panic.
+#[test]
+fn malformed() {
+    let data = compile("malformed", &["-Cdwarf-version=5"]);
+    for len in (0..data.len()).step_by(7) {
+        let _ = object::scan(&data[..len]);
+    }
+    for i in (0..data.len()).step_by(3) {
+        for byte in [0x00, 0x7f, 0x80, 0xff] {
+            let mut corrupted = data.clone();
+            corrupted[i] = byte;
+            let _ = object::scan(&corrupted);
+        }
+    }
+    // Offsets and sizes that overflow when added to
+    for i in (0..data.len().saturating_sub(8)).step_by(4) {
+        let mut corrupted = data.clone();
+        corrupted[i..i + 8].copy_from_slice(&[0xff; 8]);
+        let _ = object::scan(&corrupted);
+    }
+}
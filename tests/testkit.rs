@@ -0,0 +1,29 @@
+#![cfg(feature = "std")]
+
+use reachability::matrix::{Lto, OptLevel, Profile};
+use reachability::testkit::Snippet;
+
+#[test]
+fn links() {
+    let dir = env!("CARGO_TARGET_TMPDIR");
+    let proven = Snippet::main("testkit_proven", "fn main() {\n    if [0].is_empty() {\n        reachability::unreachable_static!();\n    }\n}\n").dir(dir);
+    proven.assert_links(Profile { opt_level: OptLevel::O1, lto: Lto::Off, debug_assertions: false });
+
+    let unproven = Snippet::main("testkit_unproven", "fn main() {\n    if std::env::args().count() > 9 {\n        reachability::unreachable_static!();\n    }\n}\n").dir(dir);
+    unproven.assert_fails_at(Profile { opt_level: OptLevel::O3, lto: Lto::Off, debug_assertions: false }, "src/main.rs:3");
+    unproven.assert_links(Profile { opt_level: OptLevel::O0, lto: Lto::Off, debug_assertions: true });
+
+    let result = std::panic::catch_unwind(|| unproven.assert_links(Profile { opt_level: OptLevel::O3, lto: Lto::Off, debug_assertions: false }));
+    let message = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.starts_with("`testkit_unproven` didn't link with opt-level=3 lto=off: it failed to link at\n    src/main.rs:3:9"), "{}", message);
+}
+
+/// Without `Snippet::dir`, each test process builds in a directory of its own.
+#[test]
+fn default_dir() {
+    let snippet = Snippet::main("testkit_default", "fn main() {}\n");
+    snippet.assert_links(Profile { opt_level: OptLevel::O0, lto: Lto::Off, debug_assertions: false });
+    let dir = std::env::temp_dir().join(format!("reachability-testkit-{}", std::process::id()));
+    assert!(dir.join("testkit_default/Cargo.toml").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,10 @@
+#![allow(clippy::unnecessary_literal_unwrap)]
+
+#[test]
+fn unwrap_static() {
+    let v = [1u8, 2];
+    assert_eq!(reachability::unwrap_static!(v.first()), &1);
+    assert_eq!(reachability::expect_static!(v[1].checked_sub(1), "v[1] > 0"), 1);
+    assert_eq!(reachability::unwrap_err_static!(v.binary_search(&0)), 0);
+    assert_eq!(reachability::expect_err_static!(v.binary_search(&3), "not found"), 2);
+}
@@ -0,0 +1,9 @@
+#![cfg(feature = "warn")]
+
+#[test]
+#[should_panic(expected = "internal error: entered unreachable code: reached")]
+fn reached() {
+    if std::env::var_os("I EXPECT THIS TO NOT EXIST KTHX").is_none() {
+        reachability::unreachable_static!("reached")
+    }
+}